@@ -0,0 +1,260 @@
+//! # Airdrop factory contract
+//!
+//! Deploys `AirdropContract` instances from an uploaded wasm hash and keeps an on-chain
+//! registry of them, so airdrops can be discovered without going through the backend.
+
+#![no_std]
+use soroban_sdk::{
+    contract, contractclient, contracterror, contractimpl, contracttype, panic_with_error,
+    symbol_short, token::TokenClient, xdr::ToXdr, Address, Bytes, BytesN, Env, Vec,
+};
+
+pub const DAY_IN_LEDGERS: u32 = 17280;
+
+pub const INSTANCE_EXTEND_AMOUNT: u32 = 7 * DAY_IN_LEDGERS;
+pub const INSTANCE_TTL_THRESHOLD: u32 = INSTANCE_EXTEND_AMOUNT - DAY_IN_LEDGERS;
+
+/// Maximum number of airdrops returned by a single `get_airdrops` call.
+pub const MAX_PAGE_SIZE: u32 = 50;
+
+#[contracttype]
+enum DataKey {
+    Admin,
+    WasmHash,
+    AirdropCount,
+    Airdrop(u32),
+}
+
+#[contracterror]
+enum FactoryError {
+    InvalidAmount = 1100,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AirdropStatus {
+    Active,
+    Ended,
+    /// The airdrop couldn't be queried, e.g. because its instance was archived
+    Unknown,
+}
+
+#[contracttype]
+struct AirdropRecord {
+    pub address: Address,
+    pub token: Address,
+    pub root_hash: BytesN<32>,
+    pub funder: Address,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AirdropInfo {
+    pub address: Address,
+    pub token: Address,
+    pub root_hash: BytesN<32>,
+    pub funder: Address,
+    pub amount: i128,
+    pub status: AirdropStatus,
+}
+
+#[contractclient(name = "AirdropClient")]
+pub trait AirdropInterface {
+    fn is_ended(e: Env) -> bool;
+}
+
+#[contract]
+pub struct AirdropFactory;
+
+#[contractimpl]
+impl AirdropFactory {
+    /// Initializes the factory with its admin and the wasm hash of the airdrop contract.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `admin` - The address allowed to deploy airdrops. It also becomes the admin of each airdrop.
+    /// * `wasm_hash` - The hash of the uploaded airdrop contract wasm.
+    pub fn __constructor(e: Env, admin: Address, wasm_hash: BytesN<32>) {
+        e.storage().instance().set(&DataKey::Admin, &admin);
+        e.storage().instance().set(&DataKey::WasmHash, &wasm_hash);
+        e.storage().instance().set(&DataKey::AirdropCount, &0u32);
+    }
+
+    /// Updates the airdrop wasm hash used by future deployments.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `wasm_hash` - The hash of the uploaded airdrop contract wasm.
+    pub fn set_wasm_hash(e: &Env, wasm_hash: BytesN<32>) {
+        Self::admin(e).require_auth();
+
+        e.storage().instance().set(&DataKey::WasmHash, &wasm_hash);
+    }
+
+    /// Deploys and funds a new airdrop, returning its address. The address is derived from
+    /// the root hash and token, see `airdrop_address`.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `funder` - The address funding the airdrop. It receives the unclaimed tokens on recovery.
+    /// * `root_hash` - The root hash of the Merkle tree.
+    /// * `token` - The address of the token to be distributed.
+    /// * `amount` - The amount of tokens transferred from `funder` to the airdrop.
+    pub fn deploy(
+        e: &Env,
+        funder: Address,
+        root_hash: BytesN<32>,
+        token: Address,
+        amount: i128,
+    ) -> Address {
+        if amount <= 0 {
+            panic_with_error!(e, FactoryError::InvalidAmount);
+        }
+
+        let admin = Self::admin(e);
+        admin.require_auth();
+        funder.require_auth();
+
+        let wasm_hash = e
+            .storage()
+            .instance()
+            .get::<_, BytesN<32>>(&DataKey::WasmHash)
+            .unwrap();
+        let address = e
+            .deployer()
+            .with_current_contract(Self::salt(e, &root_hash, &token))
+            .deploy_v2(
+                wasm_hash,
+                (root_hash.clone(), token.clone(), admin, funder.clone()),
+            );
+
+        TokenClient::new(e, &token).transfer(&funder, &address, &amount);
+
+        let index = Self::airdrop_count(e);
+        let record = AirdropRecord {
+            address: address.clone(),
+            token: token.clone(),
+            root_hash,
+            funder,
+            amount,
+        };
+        let record_key = DataKey::Airdrop(index);
+        e.storage().persistent().set(&record_key, &record);
+        e.storage().persistent().extend_ttl(
+            &record_key,
+            INSTANCE_TTL_THRESHOLD,
+            INSTANCE_EXTEND_AMOUNT,
+        );
+        e.storage()
+            .instance()
+            .set(&DataKey::AirdropCount, &(index + 1));
+
+        e.events()
+            .publish((symbol_short!("deployed"), token), (address.clone(), index));
+
+        e.storage()
+            .instance()
+            .extend_ttl(INSTANCE_TTL_THRESHOLD, INSTANCE_EXTEND_AMOUNT);
+
+        address
+    }
+
+    /// Returns the address an airdrop with the given root hash and token is (or would be) deployed at.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `root_hash` - The root hash of the Merkle tree.
+    /// * `token` - The address of the token to be distributed.
+    pub fn airdrop_address(e: &Env, root_hash: BytesN<32>, token: Address) -> Address {
+        e.deployer()
+            .with_current_contract(Self::salt(e, &root_hash, &token))
+            .deployed_address()
+    }
+
+    /// Returns the number of airdrops deployed by the factory.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    pub fn airdrop_count(e: &Env) -> u32 {
+        e.storage()
+            .instance()
+            .get::<_, u32>(&DataKey::AirdropCount)
+            .unwrap_or(0)
+    }
+
+    /// Returns up to `limit` deployed airdrops, in deployment order, starting at `start`.
+    /// Airdrops that fail to report whether they ended are returned with an `Unknown` status.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `start` - The registry index of the first airdrop to return.
+    /// * `limit` - The maximum number of airdrops to return, capped at `MAX_PAGE_SIZE`.
+    pub fn get_airdrops(e: &Env, start: u32, limit: u32) -> Vec<AirdropInfo> {
+        let end = start
+            .saturating_add(limit.min(MAX_PAGE_SIZE))
+            .min(Self::airdrop_count(e));
+
+        let mut airdrops = Vec::new(e);
+        for index in start..end {
+            let record = e
+                .storage()
+                .persistent()
+                .get::<_, AirdropRecord>(&DataKey::Airdrop(index))
+                .unwrap();
+            let status = match AirdropClient::new(e, &record.address).try_is_ended() {
+                Ok(Ok(true)) => AirdropStatus::Ended,
+                Ok(Ok(false)) => AirdropStatus::Active,
+                _ => AirdropStatus::Unknown,
+            };
+            airdrops.push_back(AirdropInfo {
+                address: record.address,
+                token: record.token,
+                root_hash: record.root_hash,
+                funder: record.funder,
+                amount: record.amount,
+                status,
+            });
+        }
+        airdrops
+    }
+
+    /// Extends the TTL of the registry records in a page, like `deploy` does when writing
+    /// them, so the registry stays readable. Records already archived must be restored first.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `start` - The registry index of the first record to extend.
+    /// * `limit` - The maximum number of records to extend, capped at `MAX_PAGE_SIZE`.
+    pub fn extend_airdrops(e: &Env, start: u32, limit: u32) {
+        let end = start
+            .saturating_add(limit.min(MAX_PAGE_SIZE))
+            .min(Self::airdrop_count(e));
+
+        for index in start..end {
+            e.storage().persistent().extend_ttl(
+                &DataKey::Airdrop(index),
+                INSTANCE_TTL_THRESHOLD,
+                INSTANCE_EXTEND_AMOUNT,
+            );
+        }
+        e.storage()
+            .instance()
+            .extend_ttl(INSTANCE_TTL_THRESHOLD, INSTANCE_EXTEND_AMOUNT);
+    }
+
+    fn admin(e: &Env) -> Address {
+        e.storage()
+            .instance()
+            .get::<_, Address>(&DataKey::Admin)
+            .unwrap()
+    }
+
+    fn salt(e: &Env, root_hash: &BytesN<32>, token: &Address) -> BytesN<32> {
+        let mut data = Bytes::from(root_hash.clone());
+        data.append(&token.clone().to_xdr(e));
+        e.crypto().sha256(&data).into()
+    }
+}
+
+mod test;
@@ -0,0 +1,277 @@
+#![cfg(test)]
+
+use crate::{
+    AirdropFactory, AirdropFactoryClient, AirdropRecord, AirdropStatus, DataKey, DAY_IN_LEDGERS,
+    INSTANCE_EXTEND_AMOUNT,
+};
+use hex_literal::hex;
+use soroban_sdk::{
+    contract, contractimpl,
+    testutils::{storage::Persistent as _, Address as _, Ledger as _, MockAuth, MockAuthInvoke},
+    vec, Address, BytesN, Env, IntoVal, Vec,
+};
+use stellar_default_impl_macro::default_impl;
+use stellar_fungible::{Base, FungibleToken};
+
+mod airdrop {
+    soroban_sdk::contractimport!(file = "../../wasms/airdrop.optimized.wasm");
+}
+
+#[contract]
+pub struct TokenContract;
+
+#[contractimpl]
+impl TokenContract {
+    pub fn __constructor(e: &Env, owner: Address, initial_supply: i128) {
+        Base::mint(e, &owner, initial_supply);
+    }
+}
+
+#[default_impl]
+#[contractimpl]
+impl FungibleToken for TokenContract {
+    type ContractType = Base;
+}
+
+fn create_token_contract<'a>(e: &Env, owner: &Address) -> TokenContractClient<'a> {
+    let address = e.register(TokenContract, (owner, 10_000i128));
+    TokenContractClient::new(e, &address)
+}
+
+fn create_factory<'a>(e: &Env, admin: &Address) -> AirdropFactoryClient<'a> {
+    let wasm_hash = e.deployer().upload_contract_wasm(airdrop::WASM);
+    let address = e.register(AirdropFactory, (admin, wasm_hash));
+    AirdropFactoryClient::new(e, &address)
+}
+
+#[test]
+fn test_deploy_airdrop() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let admin = Address::generate(&e);
+    let funder = Address::generate(&e);
+    let token_client = create_token_contract(&e, &funder);
+    let factory = create_factory(&e, &admin);
+
+    let root_hash = BytesN::from_array(
+        &e,
+        &hex!("11932105f1a4d0092e87cead3a543da5afd8adcff63f9a8ceb6c5db3c8135722"),
+    );
+    let expected_address = factory.airdrop_address(&root_hash, &token_client.address);
+
+    let address = factory.deploy(&funder, &root_hash, &token_client.address, &1000);
+
+    assert_eq!(address, expected_address);
+    assert_eq!(token_client.balance(&address), 1000);
+    assert_eq!(token_client.balance(&funder), 9000);
+    assert_eq!(factory.airdrop_count(), 1);
+
+    let airdrops = factory.get_airdrops(&0, &10);
+    assert_eq!(airdrops.len(), 1);
+    let info = airdrops.get(0).unwrap();
+    assert_eq!(info.address, address);
+    assert_eq!(info.token, token_client.address);
+    assert_eq!(info.root_hash, root_hash);
+    assert_eq!(info.funder, funder);
+    assert_eq!(info.amount, 1000);
+    assert_eq!(info.status, AirdropStatus::Active);
+
+    let airdrop_client = airdrop::Client::new(&e, &address);
+    let receiver = Address::from_str(
+        &e,
+        "CAASCQKVVBSLREPEUGPOTQZ4BC2NDBY2MW7B2LGIGFUPIY4Z3XUZRVTX",
+    );
+    let proofs = vec![
+        &e,
+        BytesN::from_array(
+            &e,
+            &hex!("fc0d9c2f46c1e910bd3af8665318714c7c97486d2a206f96236c6e7e50c080d7"),
+        ),
+        BytesN::from_array(
+            &e,
+            &hex!("c83f7b26055572e5e84c78ec4d4f45b85b71698951077baafe195279c1f30be4"),
+        ),
+    ];
+    airdrop_client.claim(&3_u32, &receiver, &100, &proofs);
+    assert_eq!(token_client.balance(&receiver), 100);
+
+    airdrop_client.recover_unclaimed();
+    assert_eq!(token_client.balance(&funder), 9900);
+
+    let info = factory.get_airdrops(&0, &10).get(0).unwrap();
+    assert_eq!(info.status, AirdropStatus::Ended);
+}
+
+#[test]
+fn test_deploy_same_root_and_token_twice() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let admin = Address::generate(&e);
+    let funder = Address::generate(&e);
+    let token_client = create_token_contract(&e, &funder);
+    let other_token_client = create_token_contract(&e, &funder);
+    let factory = create_factory(&e, &admin);
+
+    let root_hash = BytesN::from_array(&e, &[1; 32]);
+    factory.deploy(&funder, &root_hash, &token_client.address, &100);
+
+    let second_deploy = factory.try_deploy(&funder, &root_hash, &token_client.address, &100);
+    assert!(second_deploy.is_err());
+
+    let other_token_address =
+        factory.deploy(&funder, &root_hash, &other_token_client.address, &100);
+    assert_eq!(other_token_client.balance(&other_token_address), 100);
+    assert_eq!(factory.airdrop_count(), 2);
+}
+
+#[test]
+fn test_get_airdrops_pagination() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let admin = Address::generate(&e);
+    let funder = Address::generate(&e);
+    let token_client = create_token_contract(&e, &funder);
+    let factory = create_factory(&e, &admin);
+
+    let mut addresses = Vec::new(&e);
+    for i in 0..5u8 {
+        let root_hash = BytesN::from_array(&e, &[i; 32]);
+        addresses.push_back(factory.deploy(&funder, &root_hash, &token_client.address, &100));
+    }
+
+    let first_page = factory.get_airdrops(&0, &2);
+    assert_eq!(first_page.len(), 2);
+    assert_eq!(
+        first_page.get(0).unwrap().address,
+        addresses.get(0).unwrap()
+    );
+    assert_eq!(
+        first_page.get(1).unwrap().address,
+        addresses.get(1).unwrap()
+    );
+
+    let last_page = factory.get_airdrops(&4, &2);
+    assert_eq!(last_page.len(), 1);
+    assert_eq!(last_page.get(0).unwrap().address, addresses.get(4).unwrap());
+
+    assert_eq!(factory.get_airdrops(&5, &2).len(), 0);
+}
+
+#[test]
+fn test_get_airdrops_with_failing_airdrop() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let admin = Address::generate(&e);
+    let funder = Address::generate(&e);
+    let token_client = create_token_contract(&e, &funder);
+    let factory = create_factory(&e, &admin);
+
+    let root_hash = BytesN::from_array(&e, &[0; 32]);
+    factory.deploy(&funder, &root_hash, &token_client.address, &100);
+
+    // Register a record whose contract can't answer `is_ended`, like an archived airdrop.
+    e.as_contract(&factory.address, || {
+        let record = AirdropRecord {
+            address: token_client.address.clone(),
+            token: token_client.address.clone(),
+            root_hash: root_hash.clone(),
+            funder: funder.clone(),
+            amount: 100,
+        };
+        e.storage().persistent().set(&DataKey::Airdrop(1), &record);
+        e.storage().instance().set(&DataKey::AirdropCount, &2u32);
+    });
+
+    let airdrops = factory.get_airdrops(&0, &10);
+    assert_eq!(airdrops.len(), 2);
+    assert_eq!(airdrops.get(0).unwrap().status, AirdropStatus::Active);
+    assert_eq!(airdrops.get(1).unwrap().status, AirdropStatus::Unknown);
+}
+
+#[test]
+fn test_extend_airdrop_records() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let admin = Address::generate(&e);
+    let funder = Address::generate(&e);
+    let token_client = create_token_contract(&e, &funder);
+    let factory = create_factory(&e, &admin);
+
+    factory.deploy(
+        &funder,
+        &BytesN::from_array(&e, &[1; 32]),
+        &token_client.address,
+        &100,
+    );
+    let record_ttl = || {
+        e.as_contract(&factory.address, || {
+            e.storage().persistent().get_ttl(&DataKey::Airdrop(0))
+        })
+    };
+    assert_eq!(record_ttl(), INSTANCE_EXTEND_AMOUNT);
+
+    e.ledger()
+        .with_mut(|ledger| ledger.sequence_number += 2 * DAY_IN_LEDGERS);
+    assert_eq!(record_ttl(), INSTANCE_EXTEND_AMOUNT - 2 * DAY_IN_LEDGERS);
+
+    factory.extend_airdrops(&0, &10);
+    assert_eq!(record_ttl(), INSTANCE_EXTEND_AMOUNT);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1100)")]
+fn test_deploy_invalid_amount() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let admin = Address::generate(&e);
+    let funder = Address::generate(&e);
+    let token_client = create_token_contract(&e, &funder);
+    let factory = create_factory(&e, &admin);
+
+    factory.deploy(
+        &funder,
+        &BytesN::from_array(&e, &[0; 32]),
+        &token_client.address,
+        &0,
+    );
+}
+
+#[test]
+fn test_deploy_no_admin_auth() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let admin = Address::generate(&e);
+    let funder = Address::generate(&e);
+    let token_client = create_token_contract(&e, &funder);
+    let factory = create_factory(&e, &admin);
+    let root_hash = BytesN::from_array(&e, &[0; 32]);
+
+    e.set_auths(&[]);
+
+    let result = factory
+        .mock_auths(&[MockAuth {
+            address: &funder,
+            invoke: &MockAuthInvoke {
+                contract: &factory.address,
+                fn_name: "deploy",
+                args: (
+                    funder.clone(),
+                    root_hash.clone(),
+                    token_client.address.clone(),
+                    100_i128,
+                )
+                    .into_val(&e),
+                sub_invokes: &[],
+            },
+        }])
+        .try_deploy(&funder, &root_hash, &token_client.address, &100);
+    assert!(result.is_err());
+    assert_eq!(factory.airdrop_count(), 0);
+}
@@ -4,9 +4,10 @@
 
 #![no_std]
 use soroban_sdk::{
-    contract, contracterror, contractimpl, contracttype, panic_with_error,
+    contract, contractclient, contracterror, contractimpl, contracttype, panic_with_error,
+    symbol_short,
     token::{self, TokenClient},
-    Address, BytesN, Env, Vec,
+    Address, BytesN, Env, String, Vec,
 };
 use stellar_crypto::sha256::Sha256;
 use stellar_merkle_distributor::{IndexableLeaf, MerkleDistributor};
@@ -24,13 +25,28 @@ enum DataKey {
     TokenAddress,
     Admin,
     Funder,
+    Metadata,
+    BonusConfig,
+    BonusPool,
+    NftGate,
+    Allocation(Address),
+    AllocationClaimed(Address),
 }
 
 #[contracterror]
 enum AirdropError {
     Ended = 1000,
+    InvalidBonusConfig = 1001,
+    InvalidAmount = 1002,
+    MissingNft = 1003,
+    AlreadyClaimed = 1004,
+    NoAllocation = 1005,
+    InsufficientBalance = 1006,
 }
 
+/// Maximum value of `BonusConfig::odds_bps`, i.e. every claim wins a bonus.
+pub const MAX_BONUS_ODDS_BPS: u32 = 10_000;
+
 #[contracttype]
 struct Receiver {
     pub index: u32,
@@ -38,6 +54,50 @@ struct Receiver {
     pub amount: i128,
 }
 
+/// Off-chain details needed to audit the distribution: anyone can fetch the
+/// leaf list from `uri`, rebuild the Merkle tree and compare it to the root.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AirdropMetadata {
+    pub name: String,
+    pub description: String,
+    pub uri: String,
+    pub leaf_count: u32,
+}
+
+/// Lottery settings for claims: each claim has `odds_bps` in 10 000 chances of
+/// receiving an extra `amount * multiplier` from the bonus pool.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BonusConfig {
+    pub odds_bps: u32,
+    pub multiplier: u32,
+}
+
+/// Restricts claims to receivers holding a token of the `nft` contract. When
+/// `session_id` is set, receivers claim through the `_with_nft` entry points, naming a
+/// token of theirs that belongs to that session.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NftGate {
+    pub nft: Address,
+    pub session_id: Option<String>,
+}
+
+/// Mirrors `TokenData` of the `nft` contract.
+#[contracttype]
+pub struct NftTokenData {
+    pub session_id: String,
+    pub resource: String,
+}
+
+#[contractclient(name = "NftClient")]
+pub trait NftInterface {
+    fn balance(e: Env, owner: Address) -> u32;
+    fn owner_of(e: Env, token_id: u32) -> Address;
+    fn get_token_data(e: Env, token_id: u32) -> NftTokenData;
+}
+
 impl IndexableLeaf for Receiver {
     fn index(&self) -> u32 {
         self.index
@@ -85,6 +145,29 @@ impl AirdropContract {
             .unwrap_or(false)
     }
 
+    /// Returns the metadata describing the dataset the Merkle root was built from, if set.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    pub fn get_metadata(e: &Env) -> Option<AirdropMetadata> {
+        e.storage().instance().get(&DataKey::Metadata)
+    }
+
+    /// Stores the metadata describing the dataset the Merkle root was built from.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `metadata` - The name, description, leaf list URI and leaf count of the airdrop.
+    pub fn set_metadata(e: &Env, metadata: AirdropMetadata) {
+        Self::require_admin(e);
+
+        e.storage().instance().set(&DataKey::Metadata, &metadata);
+        e.events().publish(
+            (symbol_short!("metadata"),),
+            (metadata.uri, metadata.leaf_count),
+        );
+    }
+
     /// Returns whether an index has been claimed.
     ///
     /// # Arguments:
@@ -95,6 +178,7 @@ impl AirdropContract {
     }
 
     /// Claims the airdrop for a given index, transferring the specified amount of tokens to the receiver.
+    /// Returns the amount paid, including any lottery bonus.
     ///
     /// # Arguments:
     /// * `e` - The Soroban environment.
@@ -102,43 +186,250 @@ impl AirdropContract {
     /// * `receiver` - The address of the receiver who will receive the tokens.
     /// * `amount` - The amount of tokens to be claimed.
     /// * `proof` - The Merkle proof that verifies the claim.
-    pub fn claim(e: &Env, index: u32, receiver: Address, amount: i128, proof: Vec<BytesN<32>>) {
+    pub fn claim(
+        e: &Env,
+        index: u32,
+        receiver: Address,
+        amount: i128,
+        proof: Vec<BytesN<32>>,
+    ) -> i128 {
+        Self::do_claim(e, index, receiver, amount, proof, None)
+    }
+
+    /// Claims the airdrop for a given index like `claim`, proving the NFT requirement with
+    /// `token_id`, a token held by the receiver. Required when the gate names a session.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `index` - The index of the claim in the Merkle tree.
+    /// * `receiver` - The address of the receiver who will receive the tokens.
+    /// * `amount` - The amount of tokens to be claimed.
+    /// * `proof` - The Merkle proof that verifies the claim.
+    /// * `token_id` - The ID of a token of the gate's `nft` contract owned by the receiver.
+    pub fn claim_with_nft(
+        e: &Env,
+        index: u32,
+        receiver: Address,
+        amount: i128,
+        proof: Vec<BytesN<32>>,
+        token_id: u32,
+    ) -> i128 {
+        Self::do_claim(e, index, receiver, amount, proof, Some(token_id))
+    }
+
+    /// Returns the amount allocated to an address in allowlist mode, or 0 if it has none.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `address` - The address to look up.
+    pub fn get_allocation(e: &Env, address: Address) -> i128 {
+        e.storage()
+            .persistent()
+            .get::<_, i128>(&DataKey::Allocation(address))
+            .unwrap_or(0)
+    }
+
+    /// Returns whether an address has claimed its allocation in allowlist mode.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `address` - The address to look up.
+    pub fn is_allocation_claimed(e: &Env, address: Address) -> bool {
+        e.storage()
+            .persistent()
+            .has(&DataKey::AllocationClaimed(address))
+    }
+
+    /// Stores a batch of allocations for allowlist mode, replacing any unclaimed allocation
+    /// of the same addresses. An amount of 0 removes the address's allocation.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `allocations` - The addresses and the amount of tokens each one can claim.
+    pub fn add_allocations(e: &Env, allocations: Vec<(Address, i128)>) {
+        if Self::is_ended(e) {
+            panic_with_error!(e, AirdropError::Ended);
+        }
+
+        Self::require_admin(e);
+
+        for (address, amount) in allocations.iter() {
+            if amount < 0 {
+                panic_with_error!(e, AirdropError::InvalidAmount);
+            }
+            if Self::is_allocation_claimed(e, address.clone()) {
+                panic_with_error!(e, AirdropError::AlreadyClaimed);
+            }
+            let key = DataKey::Allocation(address.clone());
+            if amount == 0 {
+                e.storage().persistent().remove(&key);
+            } else {
+                e.storage().persistent().set(&key, &amount);
+            }
+            e.events()
+                .publish((symbol_short!("alloc"), address), amount);
+        }
+    }
+
+    /// Claims the allocation uploaded for the receiver in allowlist mode, without a Merkle proof.
+    /// Returns the amount paid, including any lottery bonus.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `receiver` - The address of the receiver who will receive the tokens.
+    pub fn claim_allocation(e: &Env, receiver: Address) -> i128 {
+        Self::do_claim_allocation(e, receiver, None)
+    }
+
+    /// Claims the allocation uploaded for the receiver like `claim_allocation`, proving the NFT
+    /// requirement with `token_id`, a token held by the receiver. Required when the gate names
+    /// a session.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `receiver` - The address of the receiver who will receive the tokens.
+    /// * `token_id` - The ID of a token of the gate's `nft` contract owned by the receiver.
+    pub fn claim_allocation_with_nft(e: &Env, receiver: Address, token_id: u32) -> i128 {
+        Self::do_claim_allocation(e, receiver, Some(token_id))
+    }
+
+    fn do_claim_allocation(e: &Env, receiver: Address, token_id: Option<u32>) -> i128 {
         if Self::is_ended(e) {
             panic_with_error!(e, AirdropError::Ended);
         }
 
         receiver.require_auth();
 
-        let data = Receiver {
-            index,
-            address: receiver.clone(),
-            amount,
-        };
-        Distributor::verify_and_set_claimed(e, data, proof);
+        if Self::is_allocation_claimed(e, receiver.clone()) {
+            panic_with_error!(e, AirdropError::AlreadyClaimed);
+        }
+        let amount = Self::get_allocation(e, receiver.clone());
+        if amount == 0 {
+            panic_with_error!(e, AirdropError::NoAllocation);
+        }
+        e.storage()
+            .persistent()
+            .set(&DataKey::AllocationClaimed(receiver.clone()), &true);
 
-        let token_client = Self::token_client(e);
-        token_client.transfer(&e.current_contract_address(), &receiver, &amount);
+        Self::pay_claim(e, &receiver, amount, token_id)
+    }
+
+    /// Returns the lottery bonus settings, if the bonus mode is enabled.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    pub fn get_bonus_config(e: &Env) -> Option<BonusConfig> {
+        e.storage().instance().get(&DataKey::BonusConfig)
+    }
+
+    /// Enables the lottery bonus mode, or updates its odds and multiplier.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `config` - The odds (in basis points) and the multiplier applied to the claimed amount.
+    pub fn set_bonus_config(e: &Env, config: BonusConfig) {
+        Self::require_admin(e);
+
+        if config.odds_bps > MAX_BONUS_ODDS_BPS || config.multiplier == 0 {
+            panic_with_error!(e, AirdropError::InvalidBonusConfig);
+        }
+
+        e.storage().instance().set(&DataKey::BonusConfig, &config);
+    }
 
+    /// Returns the NFT requirement for claims, if any.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    pub fn get_nft_gate(e: &Env) -> Option<NftGate> {
+        e.storage().instance().get(&DataKey::NftGate)
+    }
+
+    /// Sets or removes the NFT requirement for claims.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `gate` - The NFT contract and optional session receivers must hold a token of, or `None` to remove it.
+    pub fn set_nft_gate(e: &Env, gate: Option<NftGate>) {
+        Self::require_admin(e);
+
+        match gate {
+            Some(gate) => e.storage().instance().set(&DataKey::NftGate, &gate),
+            None => e.storage().instance().remove(&DataKey::NftGate),
+        }
+    }
+
+    /// Returns the amount of tokens left in the bonus pool.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    pub fn bonus_pool(e: &Env) -> i128 {
         e.storage()
             .instance()
-            .extend_ttl(INSTANCE_TTL_THRESHOLD, INSTANCE_EXTEND_AMOUNT);
+            .get::<_, i128>(&DataKey::BonusPool)
+            .unwrap_or(0)
     }
 
-    /// Recovers any unclaimed tokens from the contract back to the funder and disables further claims.
+    /// Transfers tokens from `from` into the contract and adds them to the bonus pool.
     ///
     /// # Arguments:
     /// * `e` - The Soroban environment.
-    pub fn recover_unclaimed(e: &Env) {
+    /// * `from` - The address funding the bonus pool.
+    /// * `amount` - The amount of tokens to add to the bonus pool.
+    pub fn fund_bonus_pool(e: &Env, from: Address, amount: i128) {
         if Self::is_ended(e) {
             panic_with_error!(e, AirdropError::Ended);
         }
+        if amount <= 0 {
+            panic_with_error!(e, AirdropError::InvalidAmount);
+        }
 
-        let admin = e
+        from.require_auth();
+
+        let token_client = Self::token_client(e);
+        token_client.transfer(&from, &e.current_contract_address(), &amount);
+
+        let pool = Self::bonus_pool(e) + amount;
+        e.storage().instance().set(&DataKey::BonusPool, &pool);
+    }
+
+    /// Takes tokens out of the bonus pool and sends them back to the funder, so the admin can
+    /// shrink the pool without ending the airdrop.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    /// * `amount` - The amount of tokens to take out of the bonus pool.
+    pub fn withdraw_bonus_pool(e: &Env, amount: i128) {
+        Self::require_admin(e);
+
+        let pool = Self::bonus_pool(e);
+        if amount <= 0 || amount > pool {
+            panic_with_error!(e, AirdropError::InvalidAmount);
+        }
+
+        let funder = e
             .storage()
             .instance()
-            .get::<_, Address>(&DataKey::Admin)
+            .get::<_, Address>(&DataKey::Funder)
             .unwrap();
-        admin.require_auth();
+        e.storage()
+            .instance()
+            .set(&DataKey::BonusPool, &(pool - amount));
+
+        let token_client = Self::token_client(e);
+        token_client.transfer(&e.current_contract_address(), &funder, &amount);
+    }
+
+    /// Recovers any unclaimed tokens from the contract back to the funder and disables further claims.
+    ///
+    /// # Arguments:
+    /// * `e` - The Soroban environment.
+    pub fn recover_unclaimed(e: &Env) {
+        if Self::is_ended(e) {
+            panic_with_error!(e, AirdropError::Ended);
+        }
+
+        Self::require_admin(e);
 
         let funder = e
             .storage()
@@ -146,6 +437,7 @@ impl AirdropContract {
             .get::<_, Address>(&DataKey::Funder)
             .unwrap();
         e.storage().instance().set(&DataKey::Ended, &true);
+        e.storage().instance().set(&DataKey::BonusPool, &0i128);
 
         let token_client = Self::token_client(e);
         let remaining = token_client.balance(&e.current_contract_address());
@@ -154,6 +446,125 @@ impl AirdropContract {
         }
     }
 
+    fn do_claim(
+        e: &Env,
+        index: u32,
+        receiver: Address,
+        amount: i128,
+        proof: Vec<BytesN<32>>,
+        token_id: Option<u32>,
+    ) -> i128 {
+        if Self::is_ended(e) {
+            panic_with_error!(e, AirdropError::Ended);
+        }
+
+        receiver.require_auth();
+
+        let data = Receiver {
+            index,
+            address: receiver.clone(),
+            amount,
+        };
+        Distributor::verify_and_set_claimed(e, data, proof);
+
+        Self::pay_claim(e, &receiver, amount, token_id)
+    }
+
+    /// Pays a verified claim of `amount` plus any drawn bonus to `receiver`, publishing the
+    /// same `claim` event for Merkle and allowlist claims. The claimed amount can't be taken
+    /// from the bonus pool. Returns the amount paid.
+    fn pay_claim(e: &Env, receiver: &Address, amount: i128, token_id: Option<u32>) -> i128 {
+        Self::check_nft_gate(e, receiver, token_id);
+
+        let token_client = Self::token_client(e);
+        let balance = token_client.balance(&e.current_contract_address());
+        if balance - Self::bonus_pool(e) < amount {
+            panic_with_error!(e, AirdropError::InsufficientBalance);
+        }
+
+        e.events()
+            .publish((symbol_short!("claim"), receiver.clone()), amount);
+
+        let bonus = Self::draw_bonus(e, amount);
+        if bonus > 0 {
+            e.events()
+                .publish((symbol_short!("bonus"), receiver.clone()), (amount, bonus));
+        }
+
+        let paid = amount + bonus;
+        token_client.transfer(&e.current_contract_address(), receiver, &paid);
+
+        e.storage()
+            .instance()
+            .extend_ttl(INSTANCE_TTL_THRESHOLD, INSTANCE_EXTEND_AMOUNT);
+        paid
+    }
+
+    /// Panics unless `receiver` satisfies the NFT requirement, if one is set. A session gate
+    /// needs `token_id`, which is checked directly instead of searching the receiver's tokens.
+    fn check_nft_gate(e: &Env, receiver: &Address, token_id: Option<u32>) {
+        let Some(gate) = Self::get_nft_gate(e) else {
+            return;
+        };
+        let nft_client = NftClient::new(e, &gate.nft);
+
+        let holds_nft = match token_id {
+            None => gate.session_id.is_none() && nft_client.balance(receiver) > 0,
+            // Burned or never minted tokens fail `owner_of`, and tokens minted without data
+            // fail `get_token_data` and belong to no session
+            Some(token_id) => {
+                matches!(nft_client.try_owner_of(&token_id), Ok(Ok(owner)) if owner == *receiver)
+                    && gate.session_id.is_none_or(|session_id| {
+                        matches!(
+                            nft_client.try_get_token_data(&token_id),
+                            Ok(Ok(data)) if data.session_id == session_id
+                        )
+                    })
+            }
+        };
+        if !holds_nft {
+            panic_with_error!(e, AirdropError::MissingNft);
+        }
+    }
+
+    /// Draws the lottery for a claim of `amount` and takes the won bonus out of the pool.
+    /// No draw happens when the bonus mode is disabled or the pool is empty.
+    ///
+    /// The draw happens in the claim transaction, so a contract claiming for itself can check
+    /// the paid amount and revert losing draws. The odds only hold for receivers that can't
+    /// do that, such as the app's smart wallets.
+    fn draw_bonus(e: &Env, amount: i128) -> i128 {
+        let Some(config) = Self::get_bonus_config(e) else {
+            return 0;
+        };
+        let pool = Self::bonus_pool(e);
+        if pool <= 0 {
+            return 0;
+        }
+
+        let roll: u64 = e.prng().gen_range(0..u64::from(MAX_BONUS_ODDS_BPS));
+        let bonus = if roll < u64::from(config.odds_bps) {
+            (amount * i128::from(config.multiplier)).min(pool)
+        } else {
+            0
+        };
+        // The pool is written whatever the outcome: the PRNG seed differs between simulation
+        // and execution, so the simulated footprint must not depend on the draw
+        e.storage()
+            .instance()
+            .set(&DataKey::BonusPool, &(pool - bonus));
+        bonus
+    }
+
+    fn require_admin(e: &Env) {
+        let admin = e
+            .storage()
+            .instance()
+            .get::<_, Address>(&DataKey::Admin)
+            .unwrap();
+        admin.require_auth();
+    }
+
     fn token_client(e: &Env) -> TokenClient<'_> {
         let token_address = e
             .storage()
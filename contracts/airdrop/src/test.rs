@@ -2,12 +2,15 @@
 
 #![cfg(test)]
 
-use crate::{AirdropContract, AirdropContractClient};
+use crate::{
+    AirdropContract, AirdropContractClient, AirdropError, AirdropMetadata, BonusConfig, NftGate,
+    NftTokenData,
+};
 use hex_literal::hex;
 use soroban_sdk::{
-    contract, contractimpl,
-    testutils::{Address as _, MockAuth, MockAuthInvoke},
-    vec, Address, BytesN, Env, IntoVal,
+    contract, contracterror, contractimpl, panic_with_error, symbol_short,
+    testutils::{Address as _, Events as _, MockAuth, MockAuthInvoke},
+    vec, Address, BytesN, Env, IntoVal, String,
 };
 use stellar_default_impl_macro::default_impl;
 use stellar_fungible::{Base, FungibleToken};
@@ -37,6 +40,62 @@ fn create_token_contract<'a>(e: &Env, owner: &Address) -> TokenContractClient<'a
     TokenContractClient::new(e, &address)
 }
 
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NftError {
+    UnsetTokenData = 5,
+    NonExistentToken = 200,
+}
+
+#[contract]
+pub struct NftContract;
+
+#[contractimpl]
+impl NftContract {
+    pub fn mint(e: &Env, to: Address, token_id: u32, session_id: String) {
+        Self::mint_without_data(e, to, token_id);
+        e.storage().persistent().set(
+            &(symbol_short!("data"), token_id),
+            &NftTokenData {
+                session_id,
+                resource: String::from_str(e, "resource"),
+            },
+        );
+    }
+
+    /// Like `mint` of the `nft` contract, which mints tokens without `TokenData`.
+    pub fn mint_without_data(e: &Env, to: Address, token_id: u32) {
+        let balance = Self::balance(e, to.clone());
+        e.storage()
+            .persistent()
+            .set(&(symbol_short!("owner"), token_id), &to);
+        e.storage()
+            .persistent()
+            .set(&(symbol_short!("balance"), to), &(balance + 1));
+    }
+
+    pub fn balance(e: &Env, owner: Address) -> u32 {
+        e.storage()
+            .persistent()
+            .get(&(symbol_short!("balance"), owner))
+            .unwrap_or(0)
+    }
+
+    pub fn owner_of(e: &Env, token_id: u32) -> Address {
+        e.storage()
+            .persistent()
+            .get(&(symbol_short!("owner"), token_id))
+            .unwrap_or_else(|| panic_with_error!(e, NftError::NonExistentToken))
+    }
+
+    pub fn get_token_data(e: &Env, token_id: u32) -> NftTokenData {
+        e.storage()
+            .persistent()
+            .get(&(symbol_short!("data"), token_id))
+            .unwrap_or_else(|| panic_with_error!(e, NftError::UnsetTokenData))
+    }
+}
+
 fn make_args(
     e: &Env,
     hash_bytes: [u8; 32],
@@ -430,3 +489,808 @@ fn test_recover_unclaimed_no_admin_auth() {
         .try_recover_unclaimed();
     assert!(result_with_random.is_err());
 }
+
+#[test]
+fn test_set_metadata() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+
+    let args = make_args(
+        &e,
+        hex!("11932105f1a4d0092e87cead3a543da5afd8adcff63f9a8ceb6c5db3c8135722"),
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+
+    assert_eq!(client.get_metadata(), None);
+
+    let metadata = AirdropMetadata {
+        name: String::from_str(&e, "Meridian 2025"),
+        description: String::from_str(&e, "XLM airdrop for event attendees"),
+        uri: String::from_str(
+            &e,
+            "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+        ),
+        leaf_count: 5,
+    };
+    client.set_metadata(&metadata);
+
+    assert_eq!(client.get_metadata(), Some(metadata));
+}
+
+#[test]
+fn test_set_metadata_no_admin_auth() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let token_owner = Address::generate(&e);
+    let admin = Address::generate(&e);
+    let funder = Address::generate(&e);
+    let token_client = create_token_contract(&e, &token_owner);
+
+    let args = make_args(
+        &e,
+        hex!("11932105f1a4d0092e87cead3a543da5afd8adcff63f9a8ceb6c5db3c8135722"),
+        token_client.address.clone(),
+        admin.clone(),
+        funder.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+
+    e.set_auths(&[]);
+
+    let metadata = AirdropMetadata {
+        name: String::from_str(&e, "Meridian 2025"),
+        description: String::from_str(&e, "XLM airdrop for event attendees"),
+        uri: String::from_str(&e, "ipfs://leaves"),
+        leaf_count: 5,
+    };
+
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &funder,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "set_metadata",
+                args: (metadata.clone(),).into_val(&e),
+                sub_invokes: &[],
+            },
+        }])
+        .try_set_metadata(&metadata);
+    assert!(result.is_err());
+    assert_eq!(client.get_metadata(), None);
+}
+
+#[test]
+fn test_claim_with_bonus_until_pool_empty() {
+    let e: Env = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+
+    let args = make_args(
+        &e,
+        hex!("9ecccb575ce934ab36a6db174e9f521137c942422b76332b047b49f5a1a58048"),
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &1000);
+
+    client.set_bonus_config(&BonusConfig {
+        odds_bps: 10_000,
+        multiplier: 2,
+    });
+    client.fund_bonus_pool(&owner, &150);
+    assert_eq!(client.bonus_pool(), 150);
+    assert_eq!(token_client.balance(&contract_id), 1150);
+
+    let receiver_1 = Address::from_str(
+        &e,
+        "CAASCQKVVBSLREPEUGPOTQZ4BC2NDBY2MW7B2LGIGFUPIY4Z3XUZRVTX",
+    );
+    let proofs_1 = vec![
+        &e,
+        hex_to_bytes(
+            &e,
+            hex!("cd9bbfb141e8c63b620238d79aabfbe5eaf16309874b3f32fc443b4f477c9b2f"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("ae7ed9c150e2d582d1db0a32dc7370c00a22405324e5b5f1c9272e57274a08f4"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("fc0d9c2f46c1e910bd3af8665318714c7c97486d2a206f96236c6e7e50c080d7"),
+        ),
+    ];
+
+    let receiver_2 = Address::from_str(
+        &e,
+        "CCAYN4HGXBYMAREFANQKKRNCIPLXYGXT7OVXDXG6APXBGKJPKARAOHAK",
+    );
+    let proofs_2 = vec![
+        &e,
+        hex_to_bytes(
+            &e,
+            hex!("bab7bc2e36db8910a5e047989f1bfb6791bb8a2d3b3218fd363969294aaac83e"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("c8b6359bcd036ed19bff1e307c7f0eeb410ec193a5a4647f7cf36fdba86af070"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("fc0d9c2f46c1e910bd3af8665318714c7c97486d2a206f96236c6e7e50c080d7"),
+        ),
+    ];
+
+    // The bonus is capped by what is left in the pool.
+    let paid = client.claim(&3_u32, &receiver_1, &100, &proofs_1);
+    assert_eq!(paid, 250);
+    assert_eq!(token_client.balance(&receiver_1), 250);
+    assert_eq!(client.bonus_pool(), 0);
+
+    // Once the pool is empty no more bonuses are drawn.
+    let paid = client.claim(&4_u32, &receiver_2, &100, &proofs_2);
+    assert_eq!(paid, 100);
+    assert_eq!(token_client.balance(&receiver_2), 100);
+    assert_eq!(token_client.balance(&contract_id), 800);
+}
+
+#[test]
+fn test_claim_without_winning_bonus() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+
+    let args = make_args(
+        &e,
+        hex!("11932105f1a4d0092e87cead3a543da5afd8adcff63f9a8ceb6c5db3c8135722"),
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &1000);
+
+    client.set_bonus_config(&BonusConfig {
+        odds_bps: 0,
+        multiplier: 1,
+    });
+    client.fund_bonus_pool(&owner, &500);
+
+    let receiver = Address::from_str(
+        &e,
+        "CAASCQKVVBSLREPEUGPOTQZ4BC2NDBY2MW7B2LGIGFUPIY4Z3XUZRVTX",
+    );
+    let proofs = vec![
+        &e,
+        hex_to_bytes(
+            &e,
+            hex!("fc0d9c2f46c1e910bd3af8665318714c7c97486d2a206f96236c6e7e50c080d7"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("c83f7b26055572e5e84c78ec4d4f45b85b71698951077baafe195279c1f30be4"),
+        ),
+    ];
+
+    let paid = client.claim(&3_u32, &receiver, &100, &proofs);
+    assert_eq!(paid, 100);
+    assert_eq!(token_client.balance(&receiver), 100);
+    assert_eq!(client.bonus_pool(), 500);
+
+    // The admin can shrink the pool without ending the airdrop.
+    let funder_balance = token_client.balance(&owner);
+    client.withdraw_bonus_pool(&200);
+    assert_eq!(client.bonus_pool(), 300);
+    assert_eq!(token_client.balance(&owner), funder_balance + 200);
+    assert_eq!(token_client.balance(&contract_id), 1200);
+    assert_eq!(
+        client.try_withdraw_bonus_pool(&301),
+        Err(Ok(AirdropError::InvalidAmount.into()))
+    );
+
+    client.recover_unclaimed();
+    assert_eq!(client.bonus_pool(), 0);
+    assert_eq!(token_client.balance(&contract_id), 0);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1006)")]
+fn test_claim_cannot_spend_bonus_pool() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+
+    let args = make_args(
+        &e,
+        hex!("11932105f1a4d0092e87cead3a543da5afd8adcff63f9a8ceb6c5db3c8135722"),
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &50);
+
+    client.set_bonus_config(&BonusConfig {
+        odds_bps: 0,
+        multiplier: 1,
+    });
+    client.fund_bonus_pool(&owner, &500);
+
+    let receiver = Address::from_str(
+        &e,
+        "CAASCQKVVBSLREPEUGPOTQZ4BC2NDBY2MW7B2LGIGFUPIY4Z3XUZRVTX",
+    );
+    let proofs = vec![
+        &e,
+        hex_to_bytes(
+            &e,
+            hex!("fc0d9c2f46c1e910bd3af8665318714c7c97486d2a206f96236c6e7e50c080d7"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("c83f7b26055572e5e84c78ec4d4f45b85b71698951077baafe195279c1f30be4"),
+        ),
+    ];
+
+    // Only 50 tokens were funded for claims, the rest belongs to the bonus pool.
+    client.claim(&3_u32, &receiver, &100, &proofs);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1001)")]
+fn test_set_bonus_config_invalid_odds() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+
+    let args = make_args(
+        &e,
+        hex!("11932105f1a4d0092e87cead3a543da5afd8adcff63f9a8ceb6c5db3c8135722"),
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+
+    client.set_bonus_config(&BonusConfig {
+        odds_bps: 10_001,
+        multiplier: 2,
+    });
+}
+
+#[test]
+fn test_claim_nft_gated() {
+    let e: Env = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+    let nft_client = NftContractClient::new(&e, &e.register(NftContract, ()));
+
+    let args = make_args(
+        &e,
+        hex!("9ecccb575ce934ab36a6db174e9f521137c942422b76332b047b49f5a1a58048"),
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &1000);
+
+    let gate = NftGate {
+        nft: nft_client.address.clone(),
+        session_id: Some(String::from_str(&e, "session_1")),
+    };
+    client.set_nft_gate(&Some(gate.clone()));
+    assert_eq!(client.get_nft_gate(), Some(gate));
+
+    let receiver_1 = Address::from_str(
+        &e,
+        "CAASCQKVVBSLREPEUGPOTQZ4BC2NDBY2MW7B2LGIGFUPIY4Z3XUZRVTX",
+    );
+    let proofs_1 = vec![
+        &e,
+        hex_to_bytes(
+            &e,
+            hex!("cd9bbfb141e8c63b620238d79aabfbe5eaf16309874b3f32fc443b4f477c9b2f"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("ae7ed9c150e2d582d1db0a32dc7370c00a22405324e5b5f1c9272e57274a08f4"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("fc0d9c2f46c1e910bd3af8665318714c7c97486d2a206f96236c6e7e50c080d7"),
+        ),
+    ];
+
+    let receiver_2 = Address::from_str(
+        &e,
+        "CCAYN4HGXBYMAREFANQKKRNCIPLXYGXT7OVXDXG6APXBGKJPKARAOHAK",
+    );
+    let proofs_2 = vec![
+        &e,
+        hex_to_bytes(
+            &e,
+            hex!("bab7bc2e36db8910a5e047989f1bfb6791bb8a2d3b3218fd363969294aaac83e"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("c8b6359bcd036ed19bff1e307c7f0eeb410ec193a5a4647f7cf36fdba86af070"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("fc0d9c2f46c1e910bd3af8665318714c7c97486d2a206f96236c6e7e50c080d7"),
+        ),
+    ];
+
+    // Receivers without any NFT cannot claim.
+    let claim = client.try_claim(&3_u32, &receiver_1, &100, &proofs_1);
+    assert!(claim.is_err());
+
+    nft_client.mint(&receiver_1, &1, &String::from_str(&e, "session_1"));
+    nft_client.mint(&receiver_2, &2, &String::from_str(&e, "session_2"));
+
+    // A session gate needs the claimant to name their token.
+    let claim = client.try_claim(&3_u32, &receiver_1, &100, &proofs_1);
+    assert!(claim.is_err());
+    client.claim_with_nft(&3_u32, &receiver_1, &100, &proofs_1, &1);
+    assert_eq!(token_client.balance(&receiver_1), 100);
+
+    // Holding a token from another session is not enough.
+    let claim = client.try_claim_with_nft(&4_u32, &receiver_2, &100, &proofs_2, &2);
+    assert!(claim.is_err());
+
+    client.set_nft_gate(&Some(NftGate {
+        nft: nft_client.address.clone(),
+        session_id: None,
+    }));
+    client.claim(&4_u32, &receiver_2, &100, &proofs_2);
+    assert_eq!(token_client.balance(&receiver_2), 100);
+}
+
+#[test]
+fn test_claim_nft_gated_with_token_without_data() {
+    let e: Env = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+    let nft_client = NftContractClient::new(&e, &e.register(NftContract, ()));
+
+    let args = make_args(
+        &e,
+        hex!("9ecccb575ce934ab36a6db174e9f521137c942422b76332b047b49f5a1a58048"),
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &1000);
+
+    client.set_nft_gate(&Some(NftGate {
+        nft: nft_client.address.clone(),
+        session_id: Some(String::from_str(&e, "session_1")),
+    }));
+
+    let receiver = Address::from_str(
+        &e,
+        "CAASCQKVVBSLREPEUGPOTQZ4BC2NDBY2MW7B2LGIGFUPIY4Z3XUZRVTX",
+    );
+    let proofs = vec![
+        &e,
+        hex_to_bytes(
+            &e,
+            hex!("cd9bbfb141e8c63b620238d79aabfbe5eaf16309874b3f32fc443b4f477c9b2f"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("ae7ed9c150e2d582d1db0a32dc7370c00a22405324e5b5f1c9272e57274a08f4"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("fc0d9c2f46c1e910bd3af8665318714c7c97486d2a206f96236c6e7e50c080d7"),
+        ),
+    ];
+
+    // A token without data doesn't belong to the session.
+    nft_client.mint_without_data(&receiver, &1);
+    let claim = client.try_claim_with_nft(&3_u32, &receiver, &100, &proofs, &1);
+    assert!(claim.is_err());
+
+    nft_client.mint(&receiver, &2, &String::from_str(&e, "session_1"));
+    client.claim_with_nft(&3_u32, &receiver, &100, &proofs, &2);
+    assert_eq!(token_client.balance(&receiver), 100);
+}
+
+#[test]
+fn test_claim_nft_gated_with_wrong_token() {
+    let e: Env = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+    let nft_client = NftContractClient::new(&e, &e.register(NftContract, ()));
+
+    let args = make_args(
+        &e,
+        hex!("11932105f1a4d0092e87cead3a543da5afd8adcff63f9a8ceb6c5db3c8135722"),
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &1000);
+
+    client.set_nft_gate(&Some(NftGate {
+        nft: nft_client.address.clone(),
+        session_id: Some(String::from_str(&e, "session_1")),
+    }));
+
+    let receiver = Address::from_str(
+        &e,
+        "CAASCQKVVBSLREPEUGPOTQZ4BC2NDBY2MW7B2LGIGFUPIY4Z3XUZRVTX",
+    );
+    let proofs = vec![
+        &e,
+        hex_to_bytes(
+            &e,
+            hex!("fc0d9c2f46c1e910bd3af8665318714c7c97486d2a206f96236c6e7e50c080d7"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("c83f7b26055572e5e84c78ec4d4f45b85b71698951077baafe195279c1f30be4"),
+        ),
+    ];
+
+    // The session badge is found however many other tokens the receiver holds.
+    nft_client.mint(&receiver, &0, &String::from_str(&e, "session_1"));
+    for token_id in 1..=20 {
+        nft_client.mint(&receiver, &token_id, &String::from_str(&e, "session_2"));
+    }
+    nft_client.mint(
+        &Address::generate(&e),
+        &21,
+        &String::from_str(&e, "session_1"),
+    );
+
+    // A token of another session, another holder's badge or an unknown token don't count.
+    for token_id in [1, 21, 22] {
+        assert_eq!(
+            client.try_claim_with_nft(&3_u32, &receiver, &100, &proofs, &token_id),
+            Err(Ok(AirdropError::MissingNft.into()))
+        );
+    }
+
+    let paid = client.claim_with_nft(&3_u32, &receiver, &100, &proofs, &0);
+    assert_eq!(paid, 100);
+    assert_eq!(token_client.balance(&receiver), 100);
+
+    // Allowlist claims prove the requirement the same way.
+    client.add_allocations(&vec![&e, (receiver.clone(), 50)]);
+    assert_eq!(
+        client.try_claim_allocation(&receiver),
+        Err(Ok(AirdropError::MissingNft.into()))
+    );
+    assert_eq!(
+        client.try_claim_allocation_with_nft(&receiver, &21),
+        Err(Ok(AirdropError::MissingNft.into()))
+    );
+    assert_eq!(client.claim_allocation_with_nft(&receiver, &0), 50);
+    assert_eq!(token_client.balance(&receiver), 150);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1003)")]
+fn test_claim_nft_gated_without_nft() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+    let nft_id = e.register(NftContract, ());
+
+    let args = make_args(
+        &e,
+        hex!("11932105f1a4d0092e87cead3a543da5afd8adcff63f9a8ceb6c5db3c8135722"),
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &1000);
+
+    client.set_nft_gate(&Some(NftGate {
+        nft: nft_id,
+        session_id: None,
+    }));
+
+    let receiver = Address::from_str(
+        &e,
+        "CAASCQKVVBSLREPEUGPOTQZ4BC2NDBY2MW7B2LGIGFUPIY4Z3XUZRVTX",
+    );
+    let proofs = vec![
+        &e,
+        hex_to_bytes(
+            &e,
+            hex!("fc0d9c2f46c1e910bd3af8665318714c7c97486d2a206f96236c6e7e50c080d7"),
+        ),
+        hex_to_bytes(
+            &e,
+            hex!("c83f7b26055572e5e84c78ec4d4f45b85b71698951077baafe195279c1f30be4"),
+        ),
+    ];
+
+    client.claim(&3_u32, &receiver, &100, &proofs);
+}
+
+#[test]
+fn test_claim_allocation() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+
+    let args = make_args(
+        &e,
+        [0; 32],
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &1000);
+
+    let receiver_1 = Address::generate(&e);
+    let receiver_2 = Address::generate(&e);
+    client.add_allocations(&vec![&e, (receiver_1.clone(), 100_i128)]);
+    client.add_allocations(&vec![&e, (receiver_2.clone(), 250_i128)]);
+    assert_eq!(client.get_allocation(&receiver_1), 100);
+    assert_eq!(client.get_allocation(&receiver_2), 250);
+
+    e.set_auths(&[]);
+
+    client
+        .mock_auths(&[MockAuth {
+            address: &receiver_1,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "claim_allocation",
+                args: (receiver_1.clone(),).into_val(&e),
+                sub_invokes: &[],
+            },
+        }])
+        .claim_allocation(&receiver_1);
+    assert_eq!(token_client.balance(&receiver_1), 100);
+    assert!(client.is_allocation_claimed(&receiver_1));
+    assert!(!client.is_allocation_claimed(&receiver_2));
+
+    let result = client.try_claim_allocation(&receiver_2);
+    assert!(result.is_err());
+    assert_eq!(token_client.balance(&contract_id), 900);
+}
+
+#[test]
+fn test_update_allocations_and_events() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+
+    let args = make_args(
+        &e,
+        [0; 32],
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &1000);
+
+    let receiver_1 = Address::generate(&e);
+    let receiver_2 = Address::generate(&e);
+    client.add_allocations(&vec![
+        &e,
+        (receiver_1.clone(), 100_i128),
+        (receiver_2.clone(), 250_i128),
+    ]);
+
+    // Unclaimed allocations can be corrected, or removed with an amount of 0.
+    client.add_allocations(&vec![
+        &e,
+        (receiver_1.clone(), 150_i128),
+        (receiver_2.clone(), 0_i128),
+    ]);
+    assert_eq!(
+        e.events().all(),
+        vec![
+            &e,
+            (
+                contract_id.clone(),
+                (symbol_short!("alloc"), receiver_1.clone()).into_val(&e),
+                150_i128.into_val(&e),
+            ),
+            (
+                contract_id.clone(),
+                (symbol_short!("alloc"), receiver_2.clone()).into_val(&e),
+                0_i128.into_val(&e),
+            ),
+        ]
+    );
+    assert_eq!(client.get_allocation(&receiver_1), 150);
+    assert_eq!(client.get_allocation(&receiver_2), 0);
+    assert!(client.try_claim_allocation(&receiver_2).is_err());
+
+    client.claim_allocation(&receiver_1);
+    let mut airdrop_events = vec![&e];
+    for event in e.events().all().iter() {
+        if event.0 == contract_id {
+            airdrop_events.push_back(event);
+        }
+    }
+    assert_eq!(
+        airdrop_events,
+        vec![
+            &e,
+            (
+                contract_id.clone(),
+                (symbol_short!("claim"), receiver_1.clone()).into_val(&e),
+                150_i128.into_val(&e),
+            ),
+        ]
+    );
+    assert_eq!(token_client.balance(&receiver_1), 150);
+
+    // Claimed allocations can't be changed anymore.
+    let result = client.try_add_allocations(&vec![&e, (receiver_1.clone(), 0_i128)]);
+    assert!(result.is_err());
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1004)")]
+fn test_claim_allocation_twice() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+
+    let args = make_args(
+        &e,
+        [0; 32],
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &1000);
+
+    let receiver = Address::generate(&e);
+    client.add_allocations(&vec![&e, (receiver.clone(), 100_i128)]);
+
+    client.claim_allocation(&receiver);
+    client.claim_allocation(&receiver);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1005)")]
+fn test_claim_allocation_not_allowlisted() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+
+    let args = make_args(
+        &e,
+        [0; 32],
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &1000);
+
+    client.claim_allocation(&Address::generate(&e));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1000)")]
+fn test_claim_allocation_after_ended() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let owner = Address::generate(&e);
+    let token_client = create_token_contract(&e, &owner);
+
+    let args = make_args(
+        &e,
+        [0; 32],
+        token_client.address.clone(),
+        owner.clone(),
+        owner.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+    token_client.transfer(&owner, &contract_id, &1000);
+
+    let receiver = Address::generate(&e);
+    client.add_allocations(&vec![&e, (receiver.clone(), 100_i128)]);
+
+    client.recover_unclaimed();
+    assert_eq!(token_client.balance(&owner), 10_000);
+
+    client.claim_allocation(&receiver);
+}
+
+#[test]
+fn test_add_allocations_no_admin_auth() {
+    let e = Env::default();
+    e.mock_all_auths_allowing_non_root_auth();
+
+    let token_owner = Address::generate(&e);
+    let admin = Address::generate(&e);
+    let funder = Address::generate(&e);
+    let token_client = create_token_contract(&e, &token_owner);
+
+    let args = make_args(
+        &e,
+        [0; 32],
+        token_client.address.clone(),
+        admin.clone(),
+        funder.clone(),
+    );
+    let contract_id = e.register(AirdropContract, args);
+    let client = AirdropContractClient::new(&e, &contract_id);
+
+    e.set_auths(&[]);
+
+    let receiver = Address::generate(&e);
+    let allocations = vec![&e, (receiver.clone(), 100_i128)];
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &receiver,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "add_allocations",
+                args: (allocations.clone(),).into_val(&e),
+                sub_invokes: &[],
+            },
+        }])
+        .try_add_allocations(&allocations);
+    assert!(result.is_err());
+    assert_eq!(client.get_allocation(&receiver), 0);
+}
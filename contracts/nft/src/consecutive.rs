@@ -0,0 +1,199 @@
+use crate::{
+    contract::Contract,
+    types::{ConsecutiveBatch, DataKey},
+};
+use soroban_sdk::{Address, Env};
+use stellar_non_fungible::NFTStorageKey;
+
+/// Records `batch` and makes its tokens the last ones of the owner's list and of the list of
+/// existing tokens. The writes don't depend on the batch amount; the caller updates the list
+/// counters.
+pub fn add_batch(env: &Env, batch: &ConsecutiveBatch) {
+    let batch_id = batch_count(env);
+    env.storage()
+        .persistent()
+        .set(&DataKey::ConsecutiveBatch(batch_id), batch);
+    env.storage()
+        .instance()
+        .set(&DataKey::ConsecutiveBatchCount, &(batch_id + 1));
+
+    // Batches starting at or after the end of a list have no tokens left in it, so the new
+    // batch replaces them
+    let owner = &batch.owner;
+    let owner_batches = partition_point(owner_batch_count(env, owner), |position| {
+        get_batch(env, owner_batch_id(env, owner, position)).owner_index < batch.owner_index
+    });
+    env.storage().persistent().set(
+        &DataKey::OwnerBatch(owner.clone(), owner_batches),
+        &batch_id,
+    );
+    env.storage().persistent().set(
+        &DataKey::OwnerBatchCount(owner.clone()),
+        &(owner_batches + 1),
+    );
+
+    let global_batches = partition_point(global_batch_count(env), |position| {
+        get_batch(env, global_batch_id(env, position)).global_index < batch.global_index
+    });
+    env.storage()
+        .persistent()
+        .set(&DataKey::GlobalBatch(global_batches), &batch_id);
+    env.storage()
+        .instance()
+        .set(&DataKey::GlobalBatchCount, &(global_batches + 1));
+}
+
+/// Returns the batch whose range contains `token_id`, if any.
+pub fn batch_of(env: &Env, token_id: u32) -> Option<ConsecutiveBatch> {
+    // Batches are recorded with increasing token IDs
+    let batches = partition_point(batch_count(env), |batch_id| {
+        get_batch(env, batch_id).first_token_id <= token_id
+    });
+    if batches == 0 {
+        return None;
+    }
+
+    let batch = get_batch(env, batches - 1);
+    (token_id - batch.first_token_id < batch.amount).then_some(batch)
+}
+
+/// Returns the owner of a batch token that was never transferred, approved or burned, which
+/// `Base` doesn't store.
+pub fn batch_token_owner(env: &Env, token_id: u32) -> Option<Address> {
+    if is_stored(env, token_id) {
+        return None;
+    }
+    batch_of(env, token_id).map(|batch| batch.owner)
+}
+
+/// Stores the owner and list positions of a batch token that was never transferred, approved
+/// or burned, so `Base` and the list updates handle it like any other token.
+pub fn store_batch_token(env: &Env, token_id: u32) {
+    if is_stored(env, token_id) {
+        return;
+    }
+    let Some(batch) = batch_of(env, token_id) else {
+        return;
+    };
+
+    let offset = token_id - batch.first_token_id;
+    env.storage()
+        .persistent()
+        .set(&NFTStorageKey::Owner(token_id), &batch.owner);
+    // Positions are already stored when a removal moved the token within a list
+    if !env
+        .storage()
+        .persistent()
+        .has(&DataKey::OwnerTokenIndex(token_id))
+    {
+        env.storage().persistent().set(
+            &DataKey::OwnerTokenIndex(token_id),
+            &(batch.owner_index + offset),
+        );
+    }
+    if !env
+        .storage()
+        .persistent()
+        .has(&DataKey::GlobalTokenIndex(token_id))
+    {
+        env.storage().persistent().set(
+            &DataKey::GlobalTokenIndex(token_id),
+            &(batch.global_index + offset),
+        );
+    }
+}
+
+/// Returns the token at `index` of the owner's list when no token was stored there, i.e. the
+/// token of a batch that still holds that position.
+pub fn owner_batch_token(env: &Env, owner: &Address, index: u32) -> Option<u32> {
+    let positions = partition_point(owner_batch_count(env, owner), |position| {
+        get_batch(env, owner_batch_id(env, owner, position)).owner_index <= index
+    });
+    if positions == 0 {
+        return None;
+    }
+
+    let batch = get_batch(env, owner_batch_id(env, owner, positions - 1));
+    let offset = index - batch.owner_index;
+    (offset < batch.amount).then_some(batch.first_token_id + offset)
+}
+
+/// Returns the token at `index` of the list of existing tokens when no token was stored
+/// there, like `owner_batch_token`.
+pub fn global_batch_token(env: &Env, index: u32) -> Option<u32> {
+    let positions = partition_point(global_batch_count(env), |position| {
+        get_batch(env, global_batch_id(env, position)).global_index <= index
+    });
+    if positions == 0 {
+        return None;
+    }
+
+    let batch = get_batch(env, global_batch_id(env, positions - 1));
+    let offset = index - batch.global_index;
+    (offset < batch.amount).then_some(batch.first_token_id + offset)
+}
+
+/// Returns whether `Base` stores the owner of `token_id`, or the token was burned.
+fn is_stored(env: &Env, token_id: u32) -> bool {
+    env.storage()
+        .persistent()
+        .has(&NFTStorageKey::Owner(token_id))
+        || Contract::is_burned(env, token_id)
+}
+
+fn get_batch(env: &Env, batch_id: u32) -> ConsecutiveBatch {
+    env.storage()
+        .persistent()
+        .get(&DataKey::ConsecutiveBatch(batch_id))
+        .unwrap()
+}
+
+fn batch_count(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::ConsecutiveBatchCount)
+        .unwrap_or(0)
+}
+
+fn owner_batch_id(env: &Env, owner: &Address, position: u32) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::OwnerBatch(owner.clone(), position))
+        .unwrap()
+}
+
+fn owner_batch_count(env: &Env, owner: &Address) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::OwnerBatchCount(owner.clone()))
+        .unwrap_or(0)
+}
+
+fn global_batch_id(env: &Env, position: u32) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::GlobalBatch(position))
+        .unwrap()
+}
+
+fn global_batch_count(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::GlobalBatchCount)
+        .unwrap_or(0)
+}
+
+/// Returns how many of the first `len` entries satisfy `pred`, which holds for a prefix of
+/// them.
+fn partition_point(len: u32, pred: impl Fn(u32) -> bool) -> u32 {
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
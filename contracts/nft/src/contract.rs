@@ -1,90 +1,928 @@
 use crate::{
+    consecutive::{
+        add_batch, batch_of, batch_token_owner, global_batch_token, owner_batch_token,
+        store_batch_token,
+    },
     errors::NonFungibleTokenContractError,
-    types::{DataKey, TokenData, TokenMetadata},
+    migration::{migrate_storage, SCHEMA_VERSION},
+    types::{
+        ConsecutiveBatch, DataKey, MintLeaf, MintVoucher, MinterInfo, RedemptionInfo, TokenData,
+        TokenIdStrategy, TokenMetadata, UserInfo,
+    },
 };
-use soroban_sdk::{contract, contractimpl, panic_with_error, vec, Address, Env, String, Vec};
+use soroban_sdk::{
+    contract, contractimpl, panic_with_error, symbol_short, vec, xdr::ToXdr, Address, BytesN, Env,
+    Map, String, Vec,
+};
+use stellar_crypto::{hasher::Hasher, merkle::Verifier, sha256::Sha256};
 use stellar_non_fungible::{
     burnable::NonFungibleBurnable, Base, NFTStorageKey, NonFungibleToken, NonFungibleTokenError,
 };
 
-#[contract]
-pub struct Contract;
-pub const WEEK_OF_LEDGERS: u32 = 60 * 60 * 24 / 5 * 7; // assumes 5 second ledger close times
+#[contract]
+pub struct Contract;
+pub const WEEK_OF_LEDGERS: u32 = 60 * 60 * 24 / 5 * 7; // assumes 5 second ledger close times
+pub const MAX_URI_LEN: usize = 256;
+pub const MAX_PAGE_SIZE: u32 = 50;
+
+#[contractimpl]
+impl Contract {
+    pub fn __constructor(
+        env: &Env,
+        owner: Address,
+        max_supply: u32,
+        metadata: TokenMetadata,
+        id_strategy: TokenIdStrategy,
+        soulbound: bool,
+    ) {
+        env.storage().instance().set(&DataKey::Owner, &owner);
+        env.storage()
+            .instance()
+            .set(&DataKey::MaxSupply, &max_supply);
+        env.storage().instance().set(&DataKey::TotalMinted, &0u32);
+        env.storage()
+            .instance()
+            .set(&DataKey::TokenIdStrategy, &id_strategy);
+        env.storage().instance().set(&DataKey::NextTokenId, &0u32);
+        env.storage()
+            .instance()
+            .set(&DataKey::Soulbound, &soulbound);
+        env.storage()
+            .instance()
+            .set(&DataKey::SchemaVersion, &SCHEMA_VERSION);
+
+        Base::set_metadata(env, metadata.base_uri, metadata.name, metadata.symbol);
+    }
+
+    /// Replaces the contract code, keeping its storage. Call `migrate` right after when the
+    /// new code changes the storage layout.
+    pub fn upgrade(env: &Env, wasm_hash: BytesN<32>) {
+        Self::only_owner(env);
+
+        env.deployer()
+            .update_current_contract_wasm(wasm_hash.clone());
+
+        env.events().publish((symbol_short!("upgrade"),), wasm_hash);
+    }
+
+    /// Converts storage written by the previous code to the layout of the current one, after an
+    /// `upgrade`. Collections already at `SCHEMA_VERSION` are left untouched.
+    pub fn migrate(env: &Env) {
+        Self::only_owner(env);
+
+        migrate_storage(env, Self::get_schema_version(env));
+    }
+
+    pub fn get_schema_version(env: &Env) -> u32 {
+        env.storage()
+            .instance()
+            .get(&DataKey::SchemaVersion)
+            .unwrap_or(1)
+    }
+
+    pub fn set_metadata_uri(env: &Env, base_uri: String) {
+        Self::only_owner(env);
+        ensure_metadata_not_frozen(env);
+
+        let metadata = Base::get_metadata(env);
+
+        Base::set_metadata(env, base_uri.clone(), metadata.name, metadata.symbol);
+
+        env.events()
+            .publish((symbol_short!("meta_uri"),), (metadata.base_uri, base_uri));
+    }
+
+    pub fn set_session_base_uri(env: &Env, session_id: String, base_uri: String) {
+        Self::only_owner(env);
+        ensure_metadata_not_frozen(env);
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::SessionBaseUri(session_id.clone()), &base_uri);
+
+        env.events()
+            .publish((symbol_short!("sess_uri"), session_id), base_uri);
+    }
+
+    /// Locks the collection and session base URIs, and the data of every token, for good.
+    pub fn freeze_metadata(env: &Env) {
+        Self::only_owner(env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::MetadataFrozen, &true);
+
+        env.events().publish((symbol_short!("meta_frz"),), ());
+    }
+
+    pub fn is_metadata_frozen(env: &Env) -> bool {
+        env.storage()
+            .instance()
+            .get(&DataKey::MetadataFrozen)
+            .unwrap_or(false)
+    }
+
+    /// Locks the data of `token_id` for good.
+    pub fn freeze_token_data(env: &Env, token_id: u32) {
+        Self::only_owner(env);
+        Self::owner_of(env, token_id);
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::TokenDataFrozen(token_id), &true);
+
+        env.events()
+            .publish((symbol_short!("data_frz"), token_id), ());
+    }
+
+    pub fn is_token_data_frozen(env: &Env, token_id: u32) -> bool {
+        Self::is_metadata_frozen(env)
+            || env
+                .storage()
+                .persistent()
+                .has(&DataKey::TokenDataFrozen(token_id))
+    }
+
+    /// Blocks transfers, burns and approvals until `unpause`, e.g. while a wallet compromise is
+    /// investigated. Minting and `recover_token` keep working.
+    pub fn pause(env: &Env) {
+        Self::only_owner(env);
+
+        env.storage().instance().set(&DataKey::Paused, &true);
+
+        env.events().publish((symbol_short!("paused"),), ());
+    }
+
+    pub fn unpause(env: &Env) {
+        Self::only_owner(env);
+
+        env.storage().instance().remove(&DataKey::Paused);
+
+        env.events().publish((symbol_short!("unpaused"),), ());
+    }
+
+    pub fn paused(env: &Env) -> bool {
+        env.storage()
+            .instance()
+            .get(&DataKey::Paused)
+            .unwrap_or(false)
+    }
+
+    /// Blocks transfers, burns and approvals of `token_id` until `unfreeze_token`, e.g. for a
+    /// stolen badge. `recover_token` can still move it back to its holder.
+    pub fn freeze_token(env: &Env, token_id: u32) {
+        Self::only_owner(env);
+        Self::owner_of(env, token_id);
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::TokenFrozen(token_id), &true);
+
+        env.events()
+            .publish((symbol_short!("frozen"), token_id), ());
+    }
+
+    pub fn unfreeze_token(env: &Env, token_id: u32) {
+        Self::only_owner(env);
+
+        env.storage()
+            .persistent()
+            .remove(&DataKey::TokenFrozen(token_id));
+
+        env.events()
+            .publish((symbol_short!("unfrozen"), token_id), ());
+    }
+
+    pub fn is_token_frozen(env: &Env, token_id: u32) -> bool {
+        env.storage()
+            .persistent()
+            .has(&DataKey::TokenFrozen(token_id))
+    }
+
+    pub fn get_session_base_uri(env: &Env, session_id: String) -> Option<String> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::SessionBaseUri(session_id))
+    }
+
+    /// Returns the number of existing tokens, like `circulating_supply`, so wallets and
+    /// indexers reading the usual supply function don't count burned tokens.
+    pub fn total_supply(env: &Env) -> u32 {
+        Self::circulating_supply(env)
+    }
+
+    /// Returns the number of tokens ever minted, including burned ones. This is what
+    /// `get_max_supply` limits.
+    pub fn total_minted(env: &Env) -> u32 {
+        env.storage().instance().get(&DataKey::TotalMinted).unwrap()
+    }
+
+    /// Returns the number of existing tokens, i.e. minted and not burned.
+    pub fn circulating_supply(env: &Env) -> u32 {
+        token_count(env)
+    }
+
+    /// Returns whether `token_id` was burned. Burned IDs can't be minted again.
+    pub fn is_burned(env: &Env, token_id: u32) -> bool {
+        env.storage()
+            .persistent()
+            .has(&DataKey::BurnedToken(token_id))
+    }
+
+    pub fn get_max_supply(env: &Env) -> u32 {
+        env.storage().instance().get(&DataKey::MaxSupply).unwrap()
+    }
+
+    /// Updates the collection supply limit, which can't go below the number of minted tokens.
+    pub fn set_max_supply(env: &Env, max_supply: u32) {
+        Self::only_owner(env);
+        ensure_supply_not_frozen(env);
+
+        if max_supply < Self::total_minted(env) {
+            panic_with_error!(env, NonFungibleTokenContractError::MaxSupplyBelowMinted);
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::MaxSupply, &max_supply);
+    }
+
+    /// Locks the collection and session supply limits for good.
+    pub fn freeze_supply(env: &Env) {
+        Self::only_owner(env);
+
+        env.storage().instance().set(&DataKey::SupplyFrozen, &true);
+    }
+
+    pub fn is_supply_frozen(env: &Env) -> bool {
+        env.storage()
+            .instance()
+            .get(&DataKey::SupplyFrozen)
+            .unwrap_or(false)
+    }
+
+    /// Caps the number of tokens minted with `session_id` data, or removes the cap when
+    /// `None`. The cap can't go below the number of tokens already minted for the session.
+    pub fn set_session_max_supply(env: &Env, session_id: String, max_supply: Option<u32>) {
+        Self::only_owner(env);
+        ensure_supply_not_frozen(env);
+
+        let key = DataKey::SessionMaxSupply(session_id.clone());
+        match max_supply {
+            Some(max_supply) => {
+                if max_supply < Self::get_session_minted(env, session_id) {
+                    panic_with_error!(env, NonFungibleTokenContractError::MaxSupplyBelowMinted);
+                }
+                env.storage().persistent().set(&key, &max_supply);
+            }
+            None => env.storage().persistent().remove(&key),
+        }
+    }
+
+    pub fn get_session_max_supply(env: &Env, session_id: String) -> Option<u32> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::SessionMaxSupply(session_id))
+    }
+
+    /// Returns the number of tokens minted with `session_id` data, including burned ones.
+    pub fn get_session_minted(env: &Env, session_id: String) -> u32 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::SessionMinted(session_id))
+            .unwrap_or(0)
+    }
+
+    /// Returns how many more tokens can be minted for `session_id`, within both the session
+    /// cap and the collection supply.
+    pub fn get_session_remaining(env: &Env, session_id: String) -> u32 {
+        let remaining = Self::get_max_supply(env) - Self::total_minted(env);
+        match Self::get_session_max_supply(env, session_id.clone()) {
+            Some(max_supply) => {
+                remaining.min(max_supply - Self::get_session_minted(env, session_id))
+            }
+            None => remaining,
+        }
+    }
+
+    fn only_owner(env: &Env) -> Address {
+        let owner: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(env, NonFungibleTokenContractError::UnsetOwner));
+
+        owner.require_auth();
+        owner
+    }
+
+    pub fn get_owner(env: &Env) -> Option<Address> {
+        env.storage().instance().get(&DataKey::Owner)
+    }
+
+    pub fn get_pending_owner(env: &Env) -> Option<Address> {
+        env.storage().instance().get(&DataKey::PendingOwner)
+    }
+
+    /// Proposes `new_owner` as the next owner, replacing any previous proposal. Ownership only
+    /// moves once `new_owner` calls `accept_ownership`.
+    pub fn propose_owner(env: &Env, new_owner: Address) {
+        let owner = Self::only_owner(env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::PendingOwner, &new_owner);
+
+        env.events()
+            .publish((symbol_short!("own_prop"), owner), new_owner);
+    }
+
+    pub fn accept_ownership(env: &Env) {
+        let new_owner: Address = Self::get_pending_owner(env).unwrap_or_else(|| {
+            panic_with_error!(env, NonFungibleTokenContractError::NoPendingOwner)
+        });
+        new_owner.require_auth();
+
+        // A pending owner is only set while there is an owner
+        let previous_owner = Self::get_owner(env).unwrap();
+        env.storage().instance().set(&DataKey::Owner, &new_owner);
+        env.storage().instance().remove(&DataKey::PendingOwner);
+
+        env.events()
+            .publish((symbol_short!("own_xfer"), previous_owner), new_owner);
+    }
+
+    /// Removes the owner for good. Owner-only functions can't be called anymore, while
+    /// registered minters keep their roles.
+    pub fn renounce_ownership(env: &Env) {
+        let owner = Self::only_owner(env);
+
+        env.storage().instance().remove(&DataKey::Owner);
+        env.storage().instance().remove(&DataKey::PendingOwner);
+
+        env.events().publish((symbol_short!("renounce"),), owner);
+    }
+
+    /// Registers `minter`, or replaces its quota and expiry if it is already registered.
+    pub fn add_minter(
+        env: &Env,
+        minter: Address,
+        quota: Option<u32>,
+        expires_at_ledger: Option<u32>,
+    ) {
+        Self::only_owner(env);
+
+        env.storage().persistent().set(
+            &DataKey::Minter(minter),
+            &MinterInfo {
+                quota,
+                expires_at_ledger,
+            },
+        );
+    }
+
+    pub fn remove_minter(env: &Env, minter: Address) {
+        Self::only_owner(env);
+
+        env.storage().persistent().remove(&DataKey::Minter(minter));
+    }
+
+    pub fn get_minter(env: &Env, minter: Address) -> Option<MinterInfo> {
+        env.storage().persistent().get(&DataKey::Minter(minter))
+    }
+
+    /// Returns whether `minter` is registered and not expired. The owner is always a minter.
+    pub fn is_minter(env: &Env, minter: Address) -> bool {
+        if Self::get_owner(env) == Some(minter.clone()) {
+            return true;
+        }
+
+        Self::get_minter(env, minter).is_some_and(|info| {
+            info.expires_at_ledger
+                .is_none_or(|expires_at_ledger| env.ledger().sequence() <= expires_at_ledger)
+        })
+    }
+
+    /// Requires `minter` auth and takes `count` mints from its quota.
+    fn only_minter(env: &Env, minter: &Address, count: u32) {
+        minter.require_auth();
+        use_minter_quota(env, minter, count);
+    }
+
+    /// Sets the ed25519 public key whose signatures `redeem_voucher` accepts on behalf of
+    /// `minter`, or stops accepting vouchers from `minter` when `None`.
+    pub fn set_voucher_signer(env: &Env, minter: Address, public_key: Option<BytesN<32>>) {
+        minter.require_auth();
+        if !Self::is_minter(env, minter.clone()) {
+            panic_with_error!(env, NonFungibleTokenContractError::NotMinter);
+        }
+
+        let key = DataKey::VoucherSigner(minter);
+        match public_key {
+            Some(public_key) => env.storage().persistent().set(&key, &public_key),
+            None => env.storage().persistent().remove(&key),
+        }
+    }
+
+    pub fn get_voucher_signer(env: &Env, minter: Address) -> Option<BytesN<32>> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::VoucherSigner(minter))
+    }
+
+    /// Mints the token described by `voucher` to its recipient. `signature` must be the
+    /// minter's signature of `(contract_address, voucher)` encoded as XDR, and the mint counts
+    /// against the minter's quota.
+    pub fn redeem_voucher(env: &Env, voucher: MintVoucher, signature: BytesN<64>) -> u32 {
+        voucher.to.require_auth();
+
+        if env.ledger().sequence() > voucher.expires_at_ledger {
+            panic_with_error!(env, NonFungibleTokenContractError::VoucherExpired);
+        }
+        if Self::is_voucher_used(env, voucher.minter.clone(), voucher.nonce) {
+            panic_with_error!(env, NonFungibleTokenContractError::VoucherUsed);
+        }
+
+        let public_key =
+            Self::get_voucher_signer(env, voucher.minter.clone()).unwrap_or_else(|| {
+                panic_with_error!(env, NonFungibleTokenContractError::UnsetVoucherSigner)
+            });
+        let message = (env.current_contract_address(), voucher.clone()).to_xdr(env);
+        env.crypto()
+            .ed25519_verify(&public_key, &message, &signature);
+
+        use_minter_quota(env, &voucher.minter, 1);
+        env.storage()
+            .persistent()
+            .set(&DataKey::VoucherNonce(voucher.minter, voucher.nonce), &true);
+
+        let token_id = voucher
+            .token_id
+            .unwrap_or_else(|| next_token_id(env, &voucher.to));
+        Self::do_mint(env, &voucher.to, token_id, Some(voucher.data));
+        token_id
+    }
+
+    /// Marks the voucher of `minter` with `nonce` as used, so it can't be redeemed.
+    pub fn revoke_voucher(env: &Env, minter: Address, nonce: u64) {
+        minter.require_auth();
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::VoucherNonce(minter, nonce), &true);
+    }
+
+    pub fn is_voucher_used(env: &Env, minter: Address, nonce: u64) -> bool {
+        env.storage()
+            .persistent()
+            .has(&DataKey::VoucherNonce(minter, nonce))
+    }
+
+    /// Sets the root of the Merkle tree listing who can mint a token of `session_id`, or
+    /// closes the session's allowlist when `None`. Claimed indexes are kept when the root
+    /// changes, so an updated tree must keep the indexes of existing leaves.
+    pub fn set_mint_root(env: &Env, session_id: String, root: Option<BytesN<32>>) {
+        Self::only_owner(env);
+
+        let key = DataKey::MintRoot(session_id);
+        match root {
+            Some(root) => env.storage().persistent().set(&key, &root),
+            None => env.storage().persistent().remove(&key),
+        }
+    }
+
+    pub fn get_mint_root(env: &Env, session_id: String) -> Option<BytesN<32>> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::MintRoot(session_id))
+    }
+
+    pub fn is_mint_claimed(env: &Env, session_id: String, index: u32) -> bool {
+        env.storage()
+            .persistent()
+            .has(&DataKey::MintClaimed(session_id, index))
+    }
+
+    /// Mints the token of the allowlist leaf `(index, receiver, data.session_id, data.resource)`
+    /// to `receiver`, once per leaf. The token ID is allocated like `mint_auto`.
+    pub fn claim_mint(
+        env: &Env,
+        index: u32,
+        receiver: Address,
+        data: TokenData,
+        proof: Vec<BytesN<32>>,
+    ) -> u32 {
+        receiver.require_auth();
+
+        let root = Self::get_mint_root(env, data.session_id.clone()).unwrap_or_else(|| {
+            panic_with_error!(env, NonFungibleTokenContractError::UnsetMintRoot)
+        });
+        if Self::is_mint_claimed(env, data.session_id.clone(), index) {
+            panic_with_error!(env, NonFungibleTokenContractError::MintAlreadyClaimed);
+        }
+
+        let leaf = MintLeaf {
+            index,
+            address: receiver.clone(),
+            session_id: data.session_id.clone(),
+            resource: data.resource.clone(),
+        };
+        let mut hasher = Sha256::new(env);
+        hasher.update(leaf.to_xdr(env));
+        if !Verifier::<Sha256>::verify(env, proof, root, hasher.finalize()) {
+            panic_with_error!(env, NonFungibleTokenContractError::InvalidProof);
+        }
+        env.storage()
+            .persistent()
+            .set(&DataKey::MintClaimed(data.session_id.clone(), index), &true);
+
+        let token_id = next_token_id(env, &receiver);
+        Self::do_mint(env, &receiver, token_id, Some(data));
+        token_id
+    }
+
+    fn set_token_data(env: &Env, token_id: u32, data: TokenData) {
+        env.storage()
+            .persistent()
+            .set(&DataKey::TokenData(token_id), &data);
+    }
+
+    pub fn get_token_data(env: &Env, token_id: u32) -> TokenData {
+        token_data(env, token_id).unwrap_or_else(|| {
+            panic_with_error!(env, NonFungibleTokenContractError::UnsetTokenData)
+        })
+    }
+
+    /// Replaces the data of `token_id`, e.g. to fix a `session_id` typo or a moved
+    /// `resource`. A new `session_id` counts against that session's cap instead of the
+    /// previous one.
+    pub fn update_token_data(env: &Env, token_id: u32, data: TokenData, minter: Address) {
+        minter.require_auth();
+        if !Self::is_minter(env, minter) {
+            panic_with_error!(env, NonFungibleTokenContractError::NotMinter);
+        }
+        Self::owner_of(env, token_id);
+        if Self::is_token_data_frozen(env, token_id) {
+            panic_with_error!(env, NonFungibleTokenContractError::MetadataFrozen);
+        }
+
+        let previous = token_data(env, token_id);
+        match &previous {
+            Some(previous) if previous.session_id == data.session_id => {}
+            Some(previous) => {
+                decrease_session_minted(env, &previous.session_id);
+                increase_session_minted(env, &data.session_id, 1);
+            }
+            None => increase_session_minted(env, &data.session_id, 1),
+        }
+        Self::set_token_data(env, token_id, data.clone());
+
+        let revision = Self::get_token_data_revision(env, token_id) + 1;
+        env.storage()
+            .persistent()
+            .set(&DataKey::TokenDataRevision(token_id), &revision);
+
+        env.events().publish(
+            (symbol_short!("data_upd"), token_id),
+            (previous, data, revision),
+        );
+    }
+
+    /// Returns how many times the data of `token_id` was updated since it was minted.
+    pub fn get_token_data_revision(env: &Env, token_id: u32) -> u32 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::TokenDataRevision(token_id))
+            .unwrap_or(0)
+    }
+
+    pub fn add_vendor(env: &Env, vendor: Address) {
+        Self::only_owner(env);
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::Vendor(vendor), &true);
+    }
+
+    pub fn remove_vendor(env: &Env, vendor: Address) {
+        Self::only_owner(env);
+
+        env.storage().persistent().remove(&DataKey::Vendor(vendor));
+    }
+
+    pub fn is_vendor(env: &Env, vendor: Address) -> bool {
+        env.storage().persistent().has(&DataKey::Vendor(vendor))
+    }
+
+    /// Records that the holder of `token_id` redeemed it at `vendor`'s booth. Both the holder
+    /// and the vendor must authorize it, and a token can only be redeemed once.
+    pub fn redeem(env: &Env, token_id: u32, vendor: Address) {
+        ensure_movable(env, token_id);
+        let holder = Self::owner_of(env, token_id);
+        holder.require_auth();
+        vendor.require_auth();
+
+        if !Self::is_vendor(env, vendor.clone()) {
+            panic_with_error!(env, NonFungibleTokenContractError::NotVendor);
+        }
+        if Self::is_redeemed(env, token_id) {
+            panic_with_error!(env, NonFungibleTokenContractError::AlreadyRedeemed);
+        }
+
+        let info = RedemptionInfo {
+            vendor,
+            timestamp: env.ledger().timestamp(),
+        };
+        env.storage()
+            .persistent()
+            .set(&DataKey::Redemption(token_id), &info);
+
+        env.events()
+            .publish((symbol_short!("redeem"), holder, token_id), info);
+    }
+
+    pub fn is_redeemed(env: &Env, token_id: u32) -> bool {
+        env.storage()
+            .persistent()
+            .has(&DataKey::Redemption(token_id))
+    }
+
+    pub fn redemption_info(env: &Env, token_id: u32) -> Option<RedemptionInfo> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Redemption(token_id))
+    }
+
+    /// Makes redeemed tokens soulbound, or transferable again when `soulbound` is false.
+    pub fn set_redeemed_soulbound(env: &Env, soulbound: bool) {
+        Self::only_owner(env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::RedeemedSoulbound, &soulbound);
+    }
+
+    pub fn is_redeemed_soulbound(env: &Env) -> bool {
+        env.storage()
+            .instance()
+            .get(&DataKey::RedeemedSoulbound)
+            .unwrap_or(false)
+    }
+
+    /// Lets `user` use `token_id` until the ledger timestamp `expires`, without moving
+    /// ownership, or removes the current user when `None`. `caller` must be the token owner or
+    /// an approved operator. The user is removed when the token changes hands.
+    pub fn set_user(
+        env: &Env,
+        caller: Address,
+        token_id: u32,
+        user: Option<Address>,
+        expires: u64,
+    ) {
+        ensure_movable(env, token_id);
+        caller.require_auth();
+        let owner = Self::owner_of(env, token_id);
+        Base::check_spender_approval(env, &caller, &owner, token_id);
+
+        let key = DataKey::TokenUser(token_id);
+        match &user {
+            Some(user) => env.storage().persistent().set(
+                &key,
+                &UserInfo {
+                    user: user.clone(),
+                    expires,
+                },
+            ),
+            None => env.storage().persistent().remove(&key),
+        }
+
+        env.events()
+            .publish((symbol_short!("user_upd"), token_id), (user, expires));
+    }
+
+    /// Returns the user of `token_id`, if one is set and hasn't expired.
+    pub fn user_of(env: &Env, token_id: u32) -> Option<Address> {
+        let info: Option<UserInfo> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::TokenUser(token_id));
+        info.filter(|info| env.ledger().timestamp() <= info.expires)
+            .map(|info| info.user)
+    }
+
+    /// Returns the ledger timestamp until which the user of `token_id` can use it, or 0 when
+    /// there is no user.
+    pub fn user_expires(env: &Env, token_id: u32) -> u64 {
+        let info: Option<UserInfo> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::TokenUser(token_id));
+        info.map_or(0, |info| info.expires)
+    }
+
+    /// Returns up to `limit` (capped at `MAX_PAGE_SIZE`) token IDs of `owner`, starting at
+    /// index `start`. Order isn't preserved when tokens leave the owner's list.
+    pub fn get_owner_tokens(env: &Env, owner: Address, start: u32, limit: u32) -> Vec<u32> {
+        let end = start
+            .saturating_add(limit.min(MAX_PAGE_SIZE))
+            .min(owner_token_count(env, &owner));
+
+        let mut tokens = vec![env];
+        for index in start..end {
+            tokens.push_back(Self::token_of_owner_by_index(env, owner.clone(), index));
+        }
+        tokens
+    }
+
+    pub fn token_of_owner_by_index(env: &Env, owner: Address, index: u32) -> u32 {
+        if index >= owner_token_count(env, &owner) {
+            panic_with_error!(env, NonFungibleTokenContractError::IndexOutOfBounds);
+        }
+        env.storage()
+            .persistent()
+            .get(&DataKey::OwnerToken(owner.clone(), index))
+            .or_else(|| owner_batch_token(env, &owner, index))
+            .unwrap_or_else(|| {
+                panic_with_error!(env, NonFungibleTokenContractError::IndexOutOfBounds)
+            })
+    }
+
+    /// Returns up to `limit` (capped at `MAX_PAGE_SIZE`) IDs of existing tokens, starting at
+    /// index `start`. Order isn't preserved when tokens are burned.
+    pub fn tokens(env: &Env, start: u32, limit: u32) -> Vec<u32> {
+        let end = start
+            .saturating_add(limit.min(MAX_PAGE_SIZE))
+            .min(token_count(env));
+
+        let mut tokens = vec![env];
+        for index in start..end {
+            tokens.push_back(Self::token_by_index(env, index));
+        }
+        tokens
+    }
+
+    pub fn token_by_index(env: &Env, index: u32) -> u32 {
+        if index >= token_count(env) {
+            panic_with_error!(env, NonFungibleTokenContractError::IndexOutOfBounds);
+        }
+        env.storage()
+            .persistent()
+            .get(&DataKey::GlobalToken(index))
+            .or_else(|| global_batch_token(env, index))
+            .unwrap_or_else(|| {
+                panic_with_error!(env, NonFungibleTokenContractError::IndexOutOfBounds)
+            })
+    }
+
+    /// Owner-only mint kept with the signature of the deployed collections, which the backend
+    /// calls. See `minter_mint_with_data` for minters.
+    pub fn mint_with_data(env: &Env, to: Address, token_id: u32, data: TokenData) -> u32 {
+        Self::only_owner(env);
+        Self::do_mint(env, &to, token_id, Some(data));
+        token_id
+    }
 
-#[contractimpl]
-impl Contract {
-    pub fn __constructor(env: &Env, owner: Address, max_supply: u32, metadata: TokenMetadata) {
-        env.storage().instance().set(&DataKey::Owner, &owner);
+    pub fn minter_mint_with_data(
+        env: &Env,
+        to: Address,
+        token_id: u32,
+        data: TokenData,
+        minter: Address,
+    ) -> u32 {
+        Self::only_minter(env, &minter, 1);
+        Self::do_mint(env, &to, token_id, Some(data));
+        token_id
+    }
+
+    /// Mints a token with data that can't be transferred or approved, whatever the
+    /// collection setting.
+    pub fn mint_soulbound(
+        env: &Env,
+        to: Address,
+        token_id: u32,
+        data: TokenData,
+        minter: Address,
+    ) -> u32 {
+        let token_id = Self::minter_mint_with_data(env, to, token_id, data, minter);
+        env.storage()
+            .persistent()
+            .set(&DataKey::SoulboundToken(token_id), &true);
+        token_id
+    }
+
+    pub fn is_collection_soulbound(env: &Env) -> bool {
         env.storage()
             .instance()
-            .set(&DataKey::MaxSupply, &max_supply);
-        env.storage().instance().set(&DataKey::TotalMinted, &0u32);
+            .get(&DataKey::Soulbound)
+            .unwrap_or(false)
+    }
 
-        Base::set_metadata(env, metadata.base_uri, metadata.name, metadata.symbol);
+    pub fn is_soulbound(env: &Env, token_id: u32) -> bool {
+        Self::is_collection_soulbound(env)
+            || env
+                .storage()
+                .persistent()
+                .has(&DataKey::SoulboundToken(token_id))
+            || (Self::is_redeemed_soulbound(env) && Self::is_redeemed(env, token_id))
     }
 
-    pub fn set_metadata_uri(env: &Env, base_uri: String) {
+    /// Moves a token without the holder's authorization, e.g. to recover a soulbound
+    /// token from a lost wallet.
+    pub fn recover_token(env: &Env, from: Address, to: Address, token_id: u32) {
         Self::only_owner(env);
 
-        let metadata = Base::get_metadata(env);
+        store_batch_token(env, token_id);
+        Base::update(env, Some(&from), Some(&to), token_id);
+        remove_token_from_owner_list(env, &from, token_id);
+        add_token_to_owner_list(env, &to, token_id);
+        remove_user(env, token_id);
 
-        Base::set_metadata(env, base_uri, metadata.name, metadata.symbol);
+        env.events()
+            .publish((symbol_short!("recover"), from, to), token_id);
     }
 
-    pub fn total_supply(env: &Env) -> u32 {
-        env.storage().instance().get(&DataKey::TotalMinted).unwrap()
-    }
+    /// Transfers `token_ids` from `from` to `to` with a single authorization. Every token is
+    /// checked before any of them moves.
+    pub fn batch_transfer(env: &Env, from: Address, to: Address, token_ids: Vec<u32>) {
+        from.require_auth();
 
-    pub fn get_max_supply(env: &Env) -> u32 {
-        env.storage().instance().get(&DataKey::MaxSupply).unwrap()
+        for token_id in token_ids.iter() {
+            ensure_batch_transferable(env, &from, token_id);
+        }
+        for token_id in token_ids.iter() {
+            move_token(env, &from, &to, token_id);
+        }
+        remove_tokens_from_owner_list(env, &from, &token_ids);
+        add_tokens_to_owner_list(env, &to, &token_ids);
     }
 
-    fn only_owner(env: &Env) {
-        let owner: Address = env
-            .storage()
-            .instance()
-            .get(&DataKey::Owner)
-            .unwrap_or_else(|| panic_with_error!(env, NonFungibleTokenContractError::UnsetOwner));
+    /// Transfers each `(to, token_id)` of `transfers` from `from` with a single authorization.
+    /// Every token is checked before any of them moves.
+    pub fn batch_transfer_to_many(env: &Env, from: Address, transfers: Vec<(Address, u32)>) {
+        from.require_auth();
 
-        owner.require_auth();
-    }
+        let mut token_ids = vec![env];
+        let mut tokens_by_recipient: Map<Address, Vec<u32>> = Map::new(env);
+        for (to, token_id) in transfers.iter() {
+            ensure_batch_transferable(env, &from, token_id);
+            token_ids.push_back(token_id);
 
-    fn set_token_data(env: &Env, token_id: u32, data: TokenData) {
-        env.storage()
-            .persistent()
-            .set(&DataKey::TokenData(token_id), &data);
+            let mut recipient_token_ids = tokens_by_recipient
+                .get(to.clone())
+                .unwrap_or_else(|| vec![env]);
+            recipient_token_ids.push_back(token_id);
+            tokens_by_recipient.set(to, recipient_token_ids);
+        }
+
+        for (to, token_id) in transfers.iter() {
+            move_token(env, &from, &to, token_id);
+        }
+        remove_tokens_from_owner_list(env, &from, &token_ids);
+        for (to, recipient_token_ids) in tokens_by_recipient.iter() {
+            add_tokens_to_owner_list(env, &to, &recipient_token_ids);
+        }
     }
 
-    pub fn get_token_data(env: &Env, token_id: u32) -> TokenData {
-        env.storage()
-            .persistent()
-            .get(&DataKey::TokenData(token_id))
-            .unwrap_or_else(|| {
-                panic_with_error!(env, NonFungibleTokenContractError::UnsetTokenData)
-            })
+    /// Owner-only mint kept with the signature of the deployed collections. See `minter_mint`
+    /// for minters.
+    pub fn mint(env: &Env, to: Address, token_id: u32) -> u32 {
+        Self::only_owner(env);
+        Self::do_mint(env, &to, token_id, None);
+        token_id
     }
 
-    pub fn get_owner_tokens(env: &Env, owner: Address) -> Vec<u32> {
-        env.storage()
-            .persistent()
-            .get(&DataKey::OwnerTokens(owner))
-            .unwrap_or_else(|| vec![&env])
+    pub fn minter_mint(env: &Env, to: Address, token_id: u32, minter: Address) -> u32 {
+        Self::only_minter(env, &minter, 1);
+        Self::do_mint(env, &to, token_id, None);
+        token_id
     }
 
-    pub fn mint_with_data(env: &Env, to: Address, token_id: u32, data: TokenData) -> u32 {
-        let token_id = Self::mint(env, to.clone(), token_id);
-        Self::set_token_data(env, token_id, data);
+    pub fn mint_with_data_auto(env: &Env, to: Address, data: TokenData, minter: Address) -> u32 {
+        Self::only_minter(env, &minter, 1);
+        let token_id = next_token_id(env, &to);
+        Self::do_mint(env, &to, token_id, Some(data));
         token_id
     }
 
-    pub fn mint(env: &Env, to: Address, token_id: u32) -> u32 {
-        Self::only_owner(env);
-        Self::do_mint(env, &to, token_id);
+    /// Mints a token whose ID is allocated by the contract, following the
+    /// `TokenIdStrategy` chosen at construction.
+    pub fn mint_auto(env: &Env, to: Address, minter: Address) -> u32 {
+        Self::only_minter(env, &minter, 1);
+        let token_id = next_token_id(env, &to);
+        Self::do_mint(env, &to, token_id, None);
         token_id
     }
 
-    fn do_mint(env: &Env, to: &Address, token_id: u32) {
-        let current_supply = Self::total_supply(env);
+    pub fn get_token_id_strategy(env: &Env) -> TokenIdStrategy {
+        env.storage()
+            .instance()
+            .get(&DataKey::TokenIdStrategy)
+            .unwrap_or(TokenIdStrategy::Sequential)
+    }
+
+    /// Mints `token_id` to `to` within the collection supply and, for tokens with data, the
+    /// supply of their session.
+    fn do_mint(env: &Env, to: &Address, token_id: u32, data: Option<TokenData>) {
+        let current_supply = Self::total_minted(env);
         let max_supply = Self::get_max_supply(env);
 
         if current_supply >= max_supply {
@@ -97,8 +935,22 @@ impl Contract {
         {
             panic_with_error!(env, NonFungibleTokenContractError::AlreadyMinted);
         }
-        increase_total_minted(env);
+        if Self::is_burned(env, token_id) {
+            panic_with_error!(env, NonFungibleTokenContractError::TokenBurned);
+        }
+        if batch_of(env, token_id).is_some() {
+            panic_with_error!(env, NonFungibleTokenContractError::AlreadyMinted);
+        }
+        if let Some(data) = data {
+            increase_session_minted(env, &data.session_id, 1);
+            Self::set_token_data(env, token_id, data.clone());
+            env.events()
+                .publish((symbol_short!("mint_data"), to.clone(), token_id), data);
+        }
+        increase_total_minted(env, 1);
+        record_token_id(env, token_id);
         add_token_to_owner_list(env, to, token_id);
+        add_token_to_global_list(env, token_id);
         Base::mint(env, to, token_id);
     }
 
@@ -116,13 +968,103 @@ impl Contract {
         }
     }
 
+    /// Owner-only bulk mint kept with the signature of the deployed collections. See
+    /// `minter_bulk_mint_with_data` for minters.
     pub fn bulk_mint_with_data(env: &Env, tokens: Vec<(Address, u32, TokenData)>) {
-        Self::only_owner(env);
+        let owner = Self::only_owner(env);
+        Self::do_bulk_mint(env, &tokens, owner);
+    }
+
+    pub fn minter_bulk_mint_with_data(
+        env: &Env,
+        tokens: Vec<(Address, u32, TokenData)>,
+        minter: Address,
+    ) {
+        Self::only_minter(env, &minter, tokens.len());
+        Self::do_bulk_mint(env, &tokens, minter);
+    }
 
+    fn do_bulk_mint(env: &Env, tokens: &Vec<(Address, u32, TokenData)>, minter: Address) {
         for (to, token_id, data) in tokens.iter() {
-            Self::do_mint(env, &to, token_id);
-            Self::set_token_data(env, token_id, data);
+            Self::do_mint(env, &to, token_id, Some(data));
+        }
+
+        env.events()
+            .publish((symbol_short!("bulk_mint"), minter), tokens.len());
+    }
+
+    /// Mints `amount` tokens with consecutive IDs to `to`, all with `data`, and returns the
+    /// first ID. The range starts after the highest ID minted so far. Its owner and data are
+    /// stored once for the whole range, so the cost doesn't depend on `amount`; each token
+    /// gets its own entries the first time it is transferred, approved or burned.
+    pub fn mint_consecutive(
+        env: &Env,
+        to: Address,
+        amount: u32,
+        data: Option<TokenData>,
+        minter: Address,
+    ) -> u32 {
+        Self::only_minter(env, &minter, amount);
+
+        if amount == 0 {
+            panic_with_error!(env, NonFungibleTokenContractError::InvalidAmount);
+        }
+        // Random IDs spread over the whole ID space, leaving no room after the highest one
+        if Self::get_token_id_strategy(env) == TokenIdStrategy::Random {
+            panic_with_error!(env, NonFungibleTokenContractError::ConsecutiveRandomIds);
+        }
+        if amount > Self::get_max_supply(env) - Self::total_minted(env) {
+            panic_with_error!(env, NonFungibleTokenContractError::SupplyExhausted);
+        }
+        let highest_token_id: Option<u32> = env.storage().instance().get(&DataKey::HighestTokenId);
+        let first_token_id = highest_token_id.map_or(Some(0), |token_id| token_id.checked_add(1));
+        let last_token_id = first_token_id
+            .and_then(|token_id| token_id.checked_add(amount - 1))
+            .unwrap_or_else(|| {
+                panic_with_error!(env, NonFungibleTokenContractError::TokenIdsExhausted)
+            });
+        let first_token_id = last_token_id - (amount - 1);
+
+        if let Some(data) = &data {
+            increase_session_minted(env, &data.session_id, amount);
+        }
+        increase_total_minted(env, amount);
+        record_token_id(env, last_token_id);
+
+        let owner_index = owner_token_count(env, &to);
+        let global_index = token_count(env);
+        add_batch(
+            env,
+            &ConsecutiveBatch {
+                owner: to.clone(),
+                first_token_id,
+                amount,
+                owner_index,
+                global_index,
+            },
+        );
+        if let Some(data) = &data {
+            env.storage()
+                .persistent()
+                .set(&DataKey::BatchTokenData(first_token_id), data);
         }
+        env.storage().persistent().set(
+            &DataKey::OwnerTokenCount(to.clone()),
+            &(owner_index + amount),
+        );
+        env.storage()
+            .instance()
+            .set(&DataKey::TokenCount, &(global_index + amount));
+        env.storage().persistent().set(
+            &NFTStorageKey::Balance(to.clone()),
+            &(Base::balance(env, &to) + amount),
+        );
+
+        env.events().publish(
+            (symbol_short!("cons_mint"), to),
+            (first_token_id, last_token_id, data),
+        );
+        first_token_id
     }
 }
 
@@ -134,19 +1076,25 @@ impl NonFungibleToken for Contract {
     }
 
     fn owner_of(e: &Env, token_id: u32) -> Address {
-        Base::owner_of(e, token_id)
+        batch_token_owner(e, token_id).unwrap_or_else(|| Base::owner_of(e, token_id))
     }
 
     fn transfer(e: &Env, from: Address, to: Address, token_id: u32) {
+        ensure_transferable(e, token_id);
+        store_batch_token(e, token_id);
         Base::transfer(e, &from, &to, token_id);
         remove_token_from_owner_list(e, &from, token_id);
         add_token_to_owner_list(e, &to, token_id);
+        remove_user(e, token_id);
     }
 
     fn transfer_from(e: &Env, spender: Address, from: Address, to: Address, token_id: u32) {
+        ensure_transferable(e, token_id);
+        store_batch_token(e, token_id);
         Base::transfer_from(e, &spender, &from, &to, token_id);
         remove_token_from_owner_list(e, &from, token_id);
         add_token_to_owner_list(e, &to, token_id);
+        remove_user(e, token_id);
     }
 
     fn approve(
@@ -156,10 +1104,16 @@ impl NonFungibleToken for Contract {
         token_id: u32,
         live_until_ledger: u32,
     ) {
+        ensure_transferable(e, token_id);
+        store_batch_token(e, token_id);
         Base::approve(e, &approver, &approved, token_id, live_until_ledger);
     }
 
     fn approve_for_all(e: &Env, owner: Address, operator: Address, live_until_ledger: u32) {
+        ensure_not_paused(e);
+        if Contract::is_collection_soulbound(e) {
+            panic_with_error!(e, NonFungibleTokenContractError::Soulbound);
+        }
         Base::approve_for_all(e, &owner, &operator, live_until_ledger);
     }
 
@@ -179,57 +1133,450 @@ impl NonFungibleToken for Contract {
         Base::symbol(e)
     }
 
+    /// Returns the session base URI followed by the token's `resource` when the session has
+    /// one, the `resource` itself when it is an absolute URI, and the collection base URI
+    /// followed by the token ID otherwise.
     fn token_uri(e: &Env, token_id: u32) -> String {
-        Base::token_uri(e, token_id)
+        Self::owner_of(e, token_id);
+
+        let data = token_data(e, token_id);
+        if let Some(data) = data.filter(|data| !data.resource.is_empty()) {
+            if let Some(base_uri) = Self::get_session_base_uri(e, data.session_id) {
+                return concat_strings(e, &base_uri, &data.resource);
+            }
+            if is_absolute_uri(e, &data.resource) {
+                return data.resource;
+            }
+        }
+
+        let base_uri = Base::get_metadata(e).base_uri;
+        if base_uri.is_empty() {
+            return base_uri;
+        }
+        concat_strings(e, &base_uri, &u32_to_string(e, token_id))
     }
 }
 
 #[contractimpl]
 impl NonFungibleBurnable for Contract {
     fn burn(e: &Env, from: Address, token_id: u32) {
+        ensure_movable(e, token_id);
+        store_batch_token(e, token_id);
         Base::burn(e, &from, token_id);
-        remove_token_from_owner_list(e, &from, token_id);
+        remove_burned_token(e, &from, token_id);
     }
 
     fn burn_from(e: &Env, spender: Address, from: Address, token_id: u32) {
+        ensure_movable(e, token_id);
+        store_batch_token(e, token_id);
         Base::burn_from(e, &spender, &from, token_id);
-        remove_token_from_owner_list(e, &from, token_id);
+        remove_burned_token(e, &from, token_id);
+    }
+}
+
+/// Takes `count` mints from the quota of `minter` after checking its role. The owner is never
+/// limited.
+fn use_minter_quota(env: &Env, minter: &Address, count: u32) {
+    if Contract::get_owner(env).as_ref() == Some(minter) {
+        return;
+    }
+
+    let mut info = Contract::get_minter(env, minter.clone())
+        .unwrap_or_else(|| panic_with_error!(env, NonFungibleTokenContractError::NotMinter));
+    if info
+        .expires_at_ledger
+        .is_some_and(|expires_at_ledger| env.ledger().sequence() > expires_at_ledger)
+    {
+        panic_with_error!(env, NonFungibleTokenContractError::MinterExpired);
+    }
+    if let Some(quota) = info.quota {
+        let remaining = quota.checked_sub(count).unwrap_or_else(|| {
+            panic_with_error!(env, NonFungibleTokenContractError::MinterQuotaExceeded)
+        });
+        info.quota = Some(remaining);
+        env.storage()
+            .persistent()
+            .set(&DataKey::Minter(minter.clone()), &info);
+    }
+}
+
+/// Drops the enumeration, soulbound, freeze, redemption, user and data entries of a burned
+/// token, and publishes its data so indexers don't have to read it before it goes away. The ID
+/// is recorded as burned so it can't be minted again.
+fn remove_burned_token(env: &Env, from: &Address, token_id: u32) {
+    let data = token_data(env, token_id);
+    remove_token_from_owner_list(env, from, token_id);
+    remove_token_from_global_list(env, token_id);
+    env.storage()
+        .persistent()
+        .remove(&DataKey::SoulboundToken(token_id));
+    env.storage()
+        .persistent()
+        .remove(&DataKey::TokenDataFrozen(token_id));
+    env.storage()
+        .persistent()
+        .remove(&DataKey::Redemption(token_id));
+    remove_user(env, token_id);
+
+    env.storage()
+        .persistent()
+        .set(&DataKey::BurnedToken(token_id), &true);
+
+    env.storage()
+        .persistent()
+        .remove(&DataKey::TokenData(token_id));
+    env.storage()
+        .persistent()
+        .remove(&DataKey::TokenDataRevision(token_id));
+    env.events()
+        .publish((symbol_short!("burn_data"), from.clone(), token_id), data);
+}
+
+fn ensure_transferable(env: &Env, token_id: u32) {
+    ensure_movable(env, token_id);
+    if Contract::is_soulbound(env, token_id) {
+        panic_with_error!(env, NonFungibleTokenContractError::Soulbound);
+    }
+}
+
+/// Checks that neither the collection nor `token_id` is frozen by the owner.
+fn ensure_movable(env: &Env, token_id: u32) {
+    ensure_not_paused(env);
+    if Contract::is_token_frozen(env, token_id) {
+        panic_with_error!(env, NonFungibleTokenContractError::TokenFrozen);
+    }
+}
+
+fn ensure_not_paused(env: &Env) {
+    if Contract::paused(env) {
+        panic_with_error!(env, NonFungibleTokenContractError::Paused);
+    }
+}
+
+fn ensure_batch_transferable(env: &Env, from: &Address, token_id: u32) {
+    store_batch_token(env, token_id);
+    if Base::owner_of(env, token_id) != *from {
+        panic_with_error!(env, NonFungibleTokenContractError::NotTokenOwner);
     }
+    ensure_transferable(env, token_id);
+}
+
+/// Moves the ownership of `token_id`, removes its user and publishes the same event as
+/// `transfer`, without touching the owner lists.
+fn move_token(env: &Env, from: &Address, to: &Address, token_id: u32) {
+    Base::update(env, Some(from), Some(to), token_id);
+    env.events().publish(
+        (symbol_short!("transfer"), from.clone(), to.clone()),
+        token_id,
+    );
+    remove_user(env, token_id);
+}
+
+/// Removes the user of a token that changed hands or was burned, publishing the same event
+/// as `set_user` with no user.
+fn remove_user(env: &Env, token_id: u32) {
+    let key = DataKey::TokenUser(token_id);
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().remove(&key);
+        env.events().publish(
+            (symbol_short!("user_upd"), token_id),
+            (None::<Address>, 0u64),
+        );
+    }
+}
+
+fn owner_token_count(env: &Env, owner: &Address) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::OwnerTokenCount(owner.clone()))
+        .unwrap_or(0)
 }
 
 fn add_token_to_owner_list(env: &Env, owner: &Address, token_id: u32) {
-    let mut tokens: Vec<u32> = env
-        .storage()
+    let index = owner_token_count(env, owner);
+    set_owner_token(env, owner, index, token_id);
+    env.storage()
+        .persistent()
+        .set(&DataKey::OwnerTokenCount(owner.clone()), &(index + 1));
+}
+
+/// Appends `token_ids` to the owner's list, writing the owner's token count once.
+fn add_tokens_to_owner_list(env: &Env, owner: &Address, token_ids: &Vec<u32>) {
+    let mut count = owner_token_count(env, owner);
+    for token_id in token_ids.iter() {
+        set_owner_token(env, owner, count, token_id);
+        count += 1;
+    }
+    env.storage()
         .persistent()
-        .get(&DataKey::OwnerTokens(owner.clone()))
-        .unwrap_or_else(|| vec![env]);
-    tokens.push_back(token_id);
+        .set(&DataKey::OwnerTokenCount(owner.clone()), &count);
+}
+
+fn set_owner_token(env: &Env, owner: &Address, index: u32, token_id: u32) {
+    env.storage()
+        .persistent()
+        .set(&DataKey::OwnerToken(owner.clone(), index), &token_id);
     env.storage()
         .persistent()
-        .set(&DataKey::OwnerTokens(owner.clone()), &tokens);
+        .set(&DataKey::OwnerTokenIndex(token_id), &index);
 }
 
 fn remove_token_from_owner_list(env: &Env, owner: &Address, token_id: u32) {
-    let mut tokens: Vec<u32> = env
+    let last_index = owner_token_count(env, owner) - 1;
+    swap_remove_owner_token(env, owner, token_id, last_index);
+    env.storage()
+        .persistent()
+        .set(&DataKey::OwnerTokenCount(owner.clone()), &last_index);
+}
+
+/// Removes `token_ids` from the owner's list, writing the owner's token count once.
+fn remove_tokens_from_owner_list(env: &Env, owner: &Address, token_ids: &Vec<u32>) {
+    let mut count = owner_token_count(env, owner);
+    for token_id in token_ids.iter() {
+        count -= 1;
+        swap_remove_owner_token(env, owner, token_id, count);
+    }
+    env.storage()
+        .persistent()
+        .set(&DataKey::OwnerTokenCount(owner.clone()), &count);
+}
+
+/// Removes `token_id` from the owner's list by moving the token at `last_index` into its
+/// slot, so the cost doesn't depend on how many tokens the owner holds. The caller updates
+/// the owner's token count.
+fn swap_remove_owner_token(env: &Env, owner: &Address, token_id: u32, last_index: u32) {
+    let index: u32 = env
+        .storage()
+        .persistent()
+        .get(&DataKey::OwnerTokenIndex(token_id))
+        .unwrap_or_else(|| {
+            panic_with_error!(env, NonFungibleTokenContractError::TokenDoesNotExist)
+        });
+
+    if index != last_index {
+        let last_token_id = Contract::token_of_owner_by_index(env, owner.clone(), last_index);
+        env.storage()
+            .persistent()
+            .set(&DataKey::OwnerToken(owner.clone(), index), &last_token_id);
+        env.storage()
+            .persistent()
+            .set(&DataKey::OwnerTokenIndex(last_token_id), &index);
+    }
+
+    env.storage()
+        .persistent()
+        .remove(&DataKey::OwnerToken(owner.clone(), last_index));
+    env.storage()
+        .persistent()
+        .remove(&DataKey::OwnerTokenIndex(token_id));
+}
+
+fn token_count(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::TokenCount)
+        .unwrap_or(0)
+}
+
+fn add_token_to_global_list(env: &Env, token_id: u32) {
+    let index = token_count(env);
+    env.storage()
+        .persistent()
+        .set(&DataKey::GlobalToken(index), &token_id);
+    env.storage()
+        .persistent()
+        .set(&DataKey::GlobalTokenIndex(token_id), &index);
+    env.storage()
+        .instance()
+        .set(&DataKey::TokenCount, &(index + 1));
+}
+
+/// Removes `token_id` from the list of existing tokens with the same swap-remove as
+/// `remove_token_from_owner_list`.
+fn remove_token_from_global_list(env: &Env, token_id: u32) {
+    let index: u32 = env
         .storage()
         .persistent()
-        .get(&DataKey::OwnerTokens(owner.clone()))
+        .get(&DataKey::GlobalTokenIndex(token_id))
         .unwrap_or_else(|| {
             panic_with_error!(env, NonFungibleTokenContractError::TokenDoesNotExist)
         });
-    if let Some(pos) = tokens.first_index_of(token_id) {
-        tokens.remove(pos);
+    let last_index = token_count(env) - 1;
+
+    if index != last_index {
+        let last_token_id = Contract::token_by_index(env, last_index);
         env.storage()
             .persistent()
-            .set(&DataKey::OwnerTokens(owner.clone()), &tokens);
+            .set(&DataKey::GlobalToken(index), &last_token_id);
+        env.storage()
+            .persistent()
+            .set(&DataKey::GlobalTokenIndex(last_token_id), &index);
+    }
+
+    env.storage()
+        .persistent()
+        .remove(&DataKey::GlobalToken(last_index));
+    env.storage()
+        .persistent()
+        .remove(&DataKey::GlobalTokenIndex(token_id));
+    env.storage()
+        .instance()
+        .set(&DataKey::TokenCount, &last_index);
+}
+
+/// Returns the first unminted ID from a starting point that depends on the strategy:
+/// the ID after the last auto-assigned one, or a pseudo-random one for `to`.
+fn next_token_id(env: &Env, to: &Address) -> u32 {
+    let strategy = Contract::get_token_id_strategy(env);
+    let counter: u32 = env
+        .storage()
+        .instance()
+        .get(&DataKey::NextTokenId)
+        .unwrap_or(0);
+    let mut token_id = match strategy {
+        TokenIdStrategy::Sequential => counter,
+        // The ID decides which entries the mint writes, so it is derived from the transaction
+        // inputs rather than `env.prng()`, whose seed differs between simulation and execution
+        TokenIdStrategy::Random => {
+            let hash = env
+                .crypto()
+                .sha256(&(to.clone(), counter).to_xdr(env))
+                .to_array();
+            u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+        }
+    };
+    // IDs minted with an explicit `token_id`, burned or in a consecutive batch may already
+    // be taken, skip them
+    loop {
+        if env
+            .storage()
+            .persistent()
+            .has(&NFTStorageKey::Owner(token_id))
+            || Contract::is_burned(env, token_id)
+        {
+            token_id = token_id.wrapping_add(1);
+        } else if let Some(batch) = batch_of(env, token_id) {
+            token_id = batch.first_token_id.wrapping_add(batch.amount);
+        } else {
+            break;
+        }
+    }
+    let next_counter = match strategy {
+        TokenIdStrategy::Sequential => token_id.wrapping_add(1),
+        TokenIdStrategy::Random => counter.wrapping_add(1),
+    };
+    env.storage()
+        .instance()
+        .set(&DataKey::NextTokenId, &next_counter);
+    token_id
+}
+
+fn ensure_metadata_not_frozen(env: &Env) {
+    if Contract::is_metadata_frozen(env) {
+        panic_with_error!(env, NonFungibleTokenContractError::MetadataFrozen);
+    }
+}
+
+fn ensure_supply_not_frozen(env: &Env) {
+    if Contract::is_supply_frozen(env) {
+        panic_with_error!(env, NonFungibleTokenContractError::SupplyFrozen);
+    }
+}
+
+fn increase_session_minted(env: &Env, session_id: &String, count: u32) {
+    let minted = Contract::get_session_minted(env, session_id.clone());
+    if Contract::get_session_max_supply(env, session_id.clone())
+        .is_some_and(|max_supply| count > max_supply.saturating_sub(minted))
+    {
+        panic_with_error!(env, NonFungibleTokenContractError::SessionSupplyExhausted);
     }
+    env.storage().persistent().set(
+        &DataKey::SessionMinted(session_id.clone()),
+        &(minted + count),
+    );
+}
+
+fn decrease_session_minted(env: &Env, session_id: &String) {
+    let minted = Contract::get_session_minted(env, session_id.clone());
+    env.storage()
+        .persistent()
+        .set(&DataKey::SessionMinted(session_id.clone()), &(minted - 1));
 }
 
-fn increase_total_minted(env: &Env) {
+fn increase_total_minted(env: &Env, count: u32) {
     // Unwrap is safe because TotalMinted is set in constructor
     let mut current_minted: u32 = env.storage().instance().get(&DataKey::TotalMinted).unwrap();
-    current_minted += 1;
+    current_minted += count;
     env.storage()
         .instance()
         .set(&DataKey::TotalMinted, &current_minted);
 }
+
+/// Records `token_id` as minted, so consecutive batches start after it. Every mint goes
+/// through `do_mint` or `mint_consecutive`, which call it, and burns never lower it, so no
+/// existing or burned ID lies after the recorded one.
+fn record_token_id(env: &Env, token_id: u32) {
+    let highest_token_id: Option<u32> = env.storage().instance().get(&DataKey::HighestTokenId);
+    if highest_token_id.is_none_or(|highest_token_id| token_id > highest_token_id) {
+        env.storage()
+            .instance()
+            .set(&DataKey::HighestTokenId, &token_id);
+    }
+}
+
+/// Returns the data of `token_id`, falling back to the data of its consecutive batch.
+fn token_data(env: &Env, token_id: u32) -> Option<TokenData> {
+    let data: Option<TokenData> = env
+        .storage()
+        .persistent()
+        .get(&DataKey::TokenData(token_id));
+    if data.is_some() || Contract::is_burned(env, token_id) {
+        return data;
+    }
+    batch_of(env, token_id).and_then(|batch| {
+        env.storage()
+            .persistent()
+            .get(&DataKey::BatchTokenData(batch.first_token_id))
+    })
+}
+
+fn concat_strings(env: &Env, prefix: &String, suffix: &String) -> String {
+    let prefix_len = prefix.len() as usize;
+    let len = prefix_len + suffix.len() as usize;
+    if len > MAX_URI_LEN {
+        panic_with_error!(env, NonFungibleTokenContractError::UriTooLong);
+    }
+
+    let mut buf = [0u8; MAX_URI_LEN];
+    prefix.copy_into_slice(&mut buf[..prefix_len]);
+    suffix.copy_into_slice(&mut buf[prefix_len..len]);
+    String::from_bytes(env, &buf[..len])
+}
+
+fn u32_to_string(env: &Env, value: u32) -> String {
+    let mut buf = [0u8; 10];
+    let mut start = buf.len();
+    let mut value = value;
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    String::from_bytes(env, &buf[start..])
+}
+
+/// Returns whether `uri` contains a scheme separator. Fails like `concat_strings` when it is
+/// too long to check, rather than treating it as relative.
+fn is_absolute_uri(env: &Env, uri: &String) -> bool {
+    let len = uri.len() as usize;
+    if len > MAX_URI_LEN {
+        panic_with_error!(env, NonFungibleTokenContractError::UriTooLong);
+    }
+
+    let mut buf = [0u8; MAX_URI_LEN];
+    uri.copy_into_slice(&mut buf[..len]);
+    buf[..len].windows(3).any(|window| window == b"://")
+}
@@ -9,4 +9,30 @@ pub enum NonFungibleTokenContractError {
     UnsetTokenData = 5,
     AlreadyMinted = 6,
     TokenDoesNotExist = 7,
+    UriTooLong = 8,
+    IndexOutOfBounds = 9,
+    Soulbound = 10,
+    NotMinter = 11,
+    MinterExpired = 12,
+    MinterQuotaExceeded = 13,
+    NoPendingOwner = 14,
+    VoucherExpired = 15,
+    VoucherUsed = 16,
+    UnsetVoucherSigner = 17,
+    UnsetMintRoot = 18,
+    MintAlreadyClaimed = 19,
+    InvalidProof = 20,
+    MaxSupplyBelowMinted = 21,
+    SupplyFrozen = 22,
+    SessionSupplyExhausted = 23,
+    NotTokenOwner = 24,
+    MetadataFrozen = 25,
+    TokenBurned = 26,
+    TokenIdsExhausted = 27,
+    InvalidAmount = 28,
+    NotVendor = 29,
+    AlreadyRedeemed = 30,
+    Paused = 31,
+    TokenFrozen = 32,
+    ConsecutiveRandomIds = 33,
 }
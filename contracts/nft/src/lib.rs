@@ -1,6 +1,8 @@
 #![no_std]
 
+pub mod consecutive;
 pub mod contract;
 pub mod errors;
+pub mod migration;
 pub mod test;
 pub mod types;
@@ -0,0 +1,16 @@
+use crate::types::DataKey;
+use soroban_sdk::Env;
+
+/// Version of the storage layout written by this code. Collections deployed before the
+/// version was stored are at version 1.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Converts storage written by previous versions of the code that can upgrade to this one.
+/// No such version changed the layout yet, so only the version is recorded.
+pub fn migrate_storage(env: &Env, from_version: u32) {
+    if from_version < SCHEMA_VERSION {
+        env.storage()
+            .instance()
+            .set(&DataKey::SchemaVersion, &SCHEMA_VERSION);
+    }
+}
@@ -2,16 +2,26 @@
 extern crate std;
 use crate::{
     contract::{Contract, ContractClient},
-    types::{TokenData, TokenMetadata},
+    errors::NonFungibleTokenContractError,
+    migration::SCHEMA_VERSION,
+    types::{MintLeaf, MintVoucher, RedemptionInfo, TokenData, TokenIdStrategy, TokenMetadata},
 };
+use ed25519_dalek::{Signer, SigningKey};
 use soroban_sdk::{
     symbol_short,
     testutils::{
-        Address as _, AuthorizedFunction, AuthorizedInvocation, EnvTestConfig, Ledger as _,
-        MockAuth, MockAuthInvoke,
+        Address as _, AuthorizedFunction, AuthorizedInvocation, EnvTestConfig, Events as _,
+        Ledger as _, MockAuth, MockAuthInvoke,
     },
-    vec, Address, Env, IntoVal, String, Symbol,
+    vec,
+    xdr::ToXdr,
+    Address, Bytes, BytesN, Env, IntoVal, String, Symbol,
 };
+use stellar_non_fungible::NonFungibleTokenError;
+
+mod legacy_nft {
+    soroban_sdk::contractimport!(file = "../../wasms/nft.optimized.wasm");
+}
 
 const INITIAL_SEQUENCE_NUMBER: u32 = 10;
 
@@ -28,6 +38,16 @@ pub fn setup_test_env() -> Env {
 }
 
 fn get_contract<'a>(env: &Env, owner: &Address, max_supply: u32) -> ContractClient<'a> {
+    get_contract_with_config(env, owner, max_supply, TokenIdStrategy::Sequential, false)
+}
+
+fn get_contract_with_config<'a>(
+    env: &Env,
+    owner: &Address,
+    max_supply: u32,
+    id_strategy: TokenIdStrategy,
+    soulbound: bool,
+) -> ContractClient<'a> {
     let address = env.register(
         Contract,
         (
@@ -38,12 +58,41 @@ fn get_contract<'a>(env: &Env, owner: &Address, max_supply: u32) -> ContractClie
                 symbol: String::from_str(env, "NFT"),
                 base_uri: String::from_str(env, "https://nft.com/"),
             },
+            id_strategy,
+            soulbound,
         ),
     );
 
     ContractClient::new(env, &address)
 }
 
+fn sign_voucher(
+    env: &Env,
+    contract: &ContractClient,
+    signing_key: &SigningKey,
+    voucher: &MintVoucher,
+) -> BytesN<64> {
+    let message = (contract.address.clone(), voucher.clone()).to_xdr(env);
+    let mut buf = std::vec![0u8; message.len() as usize];
+    message.copy_into_slice(&mut buf);
+    BytesN::from_array(env, &signing_key.sign(&buf).to_bytes())
+}
+
+fn hash_leaf(env: &Env, leaf: &MintLeaf) -> BytesN<32> {
+    env.crypto().sha256(&leaf.clone().to_xdr(env)).into()
+}
+
+fn hash_pair(env: &Env, a: &BytesN<32>, b: &BytesN<32>) -> BytesN<32> {
+    let (first, second) = if a.to_array() < b.to_array() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let mut bytes = Bytes::from(first.clone());
+    bytes.append(&Bytes::from(second.clone()));
+    env.crypto().sha256(&bytes).into()
+}
+
 #[test]
 fn test_deploy() {
     let env = setup_test_env();
@@ -94,7 +143,10 @@ fn test_mint_success() {
     assert_eq!(token_id, 123456);
     assert_eq!(contract.total_supply(), 1);
     assert_eq!(contract.owner_of(&token_id), recipient);
-    assert_eq!(contract.get_owner_tokens(&recipient), vec![&env, token_id]);
+    assert_eq!(
+        contract.get_owner_tokens(&recipient, &0, &10),
+        vec![&env, token_id]
+    );
 }
 
 #[test]
@@ -138,7 +190,7 @@ fn test_bulk_mint_success() {
     assert_eq!(contract.owner_of(&123456), recipient);
     assert_eq!(contract.owner_of(&789012), recipient);
     assert_eq!(
-        contract.get_owner_tokens(&recipient),
+        contract.get_owner_tokens(&recipient, &0, &10),
         vec![&env, 123456, 789012]
     );
 }
@@ -297,8 +349,8 @@ fn test_get_owner_tokens() {
     contract.mint(&recipient1, &2u32);
     contract.mint(&recipient2, &3u32);
 
-    let recipient1_tokens = contract.get_owner_tokens(&recipient1);
-    let recipient2_tokens = contract.get_owner_tokens(&recipient2);
+    let recipient1_tokens = contract.get_owner_tokens(&recipient1, &0, &10);
+    let recipient2_tokens = contract.get_owner_tokens(&recipient2, &0, &10);
 
     assert_eq!(recipient1_tokens.len(), 2);
     assert_eq!(recipient2_tokens.len(), 1);
@@ -316,7 +368,7 @@ fn test_get_owner_tokens_empty() {
 
     contract.mint(&recipient, &1u32);
 
-    let empty_tokens = contract.get_owner_tokens(&empty_owner);
+    let empty_tokens = contract.get_owner_tokens(&empty_owner, &0, &10);
     assert_eq!(empty_tokens.len(), 0);
 }
 
@@ -492,3 +544,1902 @@ fn test_mint_with_data_unauthorized() {
 
     contract.mint_with_data(&recipient, &1u32, &token_data);
 }
+
+#[test]
+fn test_token_uri_defaults_to_base_uri() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.mint(&recipient, &1u32);
+    contract.mint_with_data(
+        &recipient,
+        &2u32,
+        &TokenData {
+            session_id: String::from_str(&env, "session_1"),
+            resource: String::from_str(&env, "t-shirt"),
+        },
+    );
+
+    assert_eq!(
+        contract.token_uri(&1u32),
+        String::from_str(&env, "https://nft.com/1")
+    );
+    assert_eq!(
+        contract.token_uri(&2u32),
+        String::from_str(&env, "https://nft.com/2")
+    );
+}
+
+#[test]
+fn test_token_uri_from_token_data() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.mint_with_data(
+        &recipient,
+        &1u32,
+        &TokenData {
+            session_id: String::from_str(&env, "session_1"),
+            resource: String::from_str(&env, "ipfs://QmBadge/1.json"),
+        },
+    );
+    contract.mint_with_data(
+        &recipient,
+        &2u32,
+        &TokenData {
+            session_id: String::from_str(&env, "session_2"),
+            resource: String::from_str(&env, "badge.json"),
+        },
+    );
+    contract.set_session_base_uri(
+        &String::from_str(&env, "session_2"),
+        &String::from_str(&env, "ipfs://QmSession2/"),
+    );
+
+    assert_eq!(
+        contract.token_uri(&1u32),
+        String::from_str(&env, "ipfs://QmBadge/1.json")
+    );
+    assert_eq!(
+        contract.token_uri(&2u32),
+        String::from_str(&env, "ipfs://QmSession2/badge.json")
+    );
+    assert_eq!(
+        contract.get_session_base_uri(&String::from_str(&env, "session_2")),
+        Some(String::from_str(&env, "ipfs://QmSession2/"))
+    );
+}
+
+#[test]
+fn test_token_uri_too_long() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    let long_resource = std::format!("ipfs://{}", "a".repeat(256));
+    contract.mint_with_data(
+        &recipient,
+        &1u32,
+        &TokenData {
+            session_id: String::from_str(&env, "session_1"),
+            resource: String::from_str(&env, &long_resource),
+        },
+    );
+    contract.mint_with_data(
+        &recipient,
+        &2u32,
+        &TokenData {
+            session_id: String::from_str(&env, "session_2"),
+            resource: String::from_str(&env, &long_resource[7..]),
+        },
+    );
+    contract.set_session_base_uri(
+        &String::from_str(&env, "session_2"),
+        &String::from_str(&env, "ipfs://QmSession2/"),
+    );
+
+    // An absolute resource too long to check fails like a session URI, instead of falling
+    // back to the base URI.
+    assert_eq!(
+        contract.try_token_uri(&1u32),
+        Err(Ok(NonFungibleTokenContractError::UriTooLong.into()))
+    );
+    assert_eq!(
+        contract.try_token_uri(&2u32),
+        Err(Ok(NonFungibleTokenContractError::UriTooLong.into()))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_set_session_base_uri_unauthorized() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let unauthorized = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    env.mock_auths(&[MockAuth {
+        address: &unauthorized,
+        invoke: &MockAuthInvoke {
+            contract: &contract.address,
+            fn_name: "set_session_base_uri",
+            args: (&unauthorized,).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+
+    contract.set_session_base_uri(
+        &String::from_str(&env, "session_1"),
+        &String::from_str(&env, "ipfs://QmSession1/"),
+    );
+}
+
+#[test]
+fn test_mint_auto_sequential() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.mint(&recipient, &1u32);
+
+    assert_eq!(contract.mint_auto(&recipient, &owner), 0);
+    // ID 1 was minted explicitly, so it is skipped
+    assert_eq!(contract.mint_auto(&recipient, &owner), 2);
+
+    let token_data = TokenData {
+        session_id: String::from_str(&env, "session_auto"),
+        resource: String::from_str(&env, "resource_auto"),
+    };
+    let token_id = contract.mint_with_data_auto(&recipient, &token_data, &owner);
+    assert_eq!(token_id, 3);
+    assert_eq!(
+        contract.get_token_data(&token_id).session_id,
+        token_data.session_id
+    );
+
+    assert_eq!(contract.total_supply(), 4);
+    assert_eq!(
+        contract.get_owner_tokens(&recipient, &0, &10),
+        vec![&env, 1, 0, 2, 3]
+    );
+}
+
+#[test]
+fn test_mint_auto_random() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract_with_config(&env, &owner, 100u32, TokenIdStrategy::Random, false);
+
+    assert_eq!(contract.get_token_id_strategy(), TokenIdStrategy::Random);
+
+    let token_id1 = contract.mint_auto(&recipient, &owner);
+    let token_id2 = contract.mint_auto(&recipient, &owner);
+
+    assert_ne!(token_id1, token_id2);
+    // IDs only depend on the recipient and the mint counter, so simulation picks the same one
+    let hash = env.crypto().sha256(&(recipient.clone(), 1u32).to_xdr(&env));
+    assert_eq!(
+        token_id2,
+        u32::from_be_bytes(hash.to_array()[..4].try_into().unwrap())
+    );
+    assert_eq!(contract.owner_of(&token_id1), recipient);
+    assert_eq!(contract.owner_of(&token_id2), recipient);
+    assert_eq!(contract.total_supply(), 2);
+}
+
+#[test]
+#[should_panic]
+fn test_mint_auto_unauthorized() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let unauthorized = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    env.mock_auths(&[MockAuth {
+        address: &unauthorized,
+        invoke: &MockAuthInvoke {
+            contract: &contract.address,
+            fn_name: "mint_auto",
+            args: (&recipient,).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+
+    contract.mint_auto(&recipient, &owner);
+}
+
+#[test]
+fn test_token_of_owner_by_index() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let new_owner = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.mint(&recipient, &10u32);
+    contract.mint(&recipient, &20u32);
+    contract.mint(&recipient, &30u32);
+
+    assert_eq!(contract.token_of_owner_by_index(&recipient, &0), 10);
+    assert_eq!(contract.token_of_owner_by_index(&recipient, &2), 30);
+
+    // The last token is moved into the slot of the transferred one
+    contract.transfer(&recipient, &new_owner, &10u32);
+
+    assert_eq!(
+        contract.get_owner_tokens(&recipient, &0, &10),
+        vec![&env, 30, 20]
+    );
+    assert_eq!(
+        contract.get_owner_tokens(&new_owner, &0, &10),
+        vec![&env, 10]
+    );
+    assert!(contract
+        .try_token_of_owner_by_index(&recipient, &2)
+        .is_err());
+
+    contract.burn(&recipient, &20u32);
+
+    assert_eq!(
+        contract.get_owner_tokens(&recipient, &0, &10),
+        vec![&env, 30]
+    );
+}
+
+#[test]
+fn test_get_owner_tokens_pagination() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    for token_id in 0..5u32 {
+        contract.mint(&recipient, &token_id);
+    }
+
+    assert_eq!(
+        contract.get_owner_tokens(&recipient, &0, &2),
+        vec![&env, 0, 1]
+    );
+    assert_eq!(
+        contract.get_owner_tokens(&recipient, &2, &2),
+        vec![&env, 2, 3]
+    );
+    assert_eq!(contract.get_owner_tokens(&recipient, &4, &2), vec![&env, 4]);
+    assert_eq!(contract.get_owner_tokens(&recipient, &5, &2).len(), 0);
+}
+
+#[test]
+fn test_owner_enumeration_cost_is_flat() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let small_holder = Address::generate(&env);
+    let large_holder = Address::generate(&env);
+    let receiver = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 1000u32);
+
+    contract.mint(&small_holder, &0u32);
+    for token_id in 1..=200u32 {
+        contract.mint(&large_holder, &token_id);
+    }
+
+    env.cost_estimate().budget().reset_default();
+    contract.mint(&small_holder, &1000u32);
+    let small_mint_cost = env.cost_estimate().budget().cpu_instruction_cost();
+
+    env.cost_estimate().budget().reset_default();
+    contract.mint(&large_holder, &1001u32);
+    let large_mint_cost = env.cost_estimate().budget().cpu_instruction_cost();
+
+    env.cost_estimate().budget().reset_default();
+    contract.transfer(&small_holder, &receiver, &0u32);
+    let small_transfer_cost = env.cost_estimate().budget().cpu_instruction_cost();
+
+    env.cost_estimate().budget().reset_default();
+    contract.transfer(&large_holder, &receiver, &1u32);
+    let large_transfer_cost = env.cost_estimate().budget().cpu_instruction_cost();
+
+    assert!(large_mint_cost <= small_mint_cost + small_mint_cost / 10);
+    assert!(large_transfer_cost <= small_transfer_cost + small_transfer_cost / 10);
+}
+
+#[test]
+fn test_global_token_enumeration() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.mint(&recipient1, &10u32);
+    contract.mint(&recipient1, &20u32);
+    contract.mint(&recipient2, &30u32);
+    contract.mint(&recipient2, &40u32);
+
+    assert_eq!(contract.tokens(&0, &10), vec![&env, 10, 20, 30, 40]);
+    assert_eq!(contract.token_by_index(&1), 20);
+
+    // Transfers don't change the global list
+    contract.transfer(&recipient1, &recipient2, &20u32);
+    assert_eq!(contract.tokens(&0, &10), vec![&env, 10, 20, 30, 40]);
+
+    // The last token is moved into the slot of the burned one
+    contract.burn(&recipient2, &20u32);
+    assert_eq!(contract.tokens(&0, &10), vec![&env, 10, 40, 30]);
+
+    let live_until_ledger = INITIAL_SEQUENCE_NUMBER + 3;
+    contract.approve(&recipient1, &owner, &10u32, &live_until_ledger);
+    contract.burn_from(&owner, &recipient1, &10u32);
+    assert_eq!(contract.tokens(&0, &10), vec![&env, 30, 40]);
+    assert_eq!(contract.tokens(&1, &10), vec![&env, 40]);
+    assert!(contract.try_token_by_index(&2).is_err());
+
+    contract.mint(&recipient1, &50u32);
+    assert_eq!(contract.tokens(&0, &10), vec![&env, 30, 40, 50]);
+}
+
+#[test]
+fn test_soulbound_collection() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let new_owner = Address::generate(&env);
+    let contract =
+        get_contract_with_config(&env, &owner, 100u32, TokenIdStrategy::Sequential, true);
+    let live_until_ledger = INITIAL_SEQUENCE_NUMBER + 3;
+
+    contract.mint(&recipient, &1u32);
+    contract.mint(&recipient, &2u32);
+
+    assert!(contract.is_collection_soulbound());
+    assert!(contract.is_soulbound(&1u32));
+    assert_eq!(
+        contract.try_transfer(&recipient, &new_owner, &1u32),
+        Err(Ok(NonFungibleTokenContractError::Soulbound.into()))
+    );
+    assert_eq!(
+        contract.try_transfer_from(&recipient, &recipient, &new_owner, &1u32),
+        Err(Ok(NonFungibleTokenContractError::Soulbound.into()))
+    );
+    assert_eq!(
+        contract.try_approve(&recipient, &new_owner, &1u32, &live_until_ledger),
+        Err(Ok(NonFungibleTokenContractError::Soulbound.into()))
+    );
+    assert_eq!(
+        contract.try_approve_for_all(&recipient, &new_owner, &live_until_ledger),
+        Err(Ok(NonFungibleTokenContractError::Soulbound.into()))
+    );
+
+    contract.recover_token(&recipient, &new_owner, &1u32);
+    assert_eq!(contract.owner_of(&1u32), new_owner);
+    assert_eq!(
+        contract.get_owner_tokens(&new_owner, &0, &10),
+        vec![&env, 1]
+    );
+
+    contract.burn(&recipient, &2u32);
+    assert_eq!(contract.balance(&recipient), 0);
+}
+
+#[test]
+fn test_soulbound_token() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let new_owner = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    let token_data = TokenData {
+        session_id: String::from_str(&env, "session_1"),
+        resource: String::from_str(&env, "badge"),
+    };
+    contract.mint_soulbound(&recipient, &1u32, &token_data, &owner);
+    contract.mint(&recipient, &2u32);
+
+    assert!(!contract.is_collection_soulbound());
+    assert!(contract.is_soulbound(&1u32));
+    assert!(!contract.is_soulbound(&2u32));
+    assert_eq!(
+        contract.try_transfer(&recipient, &new_owner, &1u32),
+        Err(Ok(NonFungibleTokenContractError::Soulbound.into()))
+    );
+
+    contract.transfer(&recipient, &new_owner, &2u32);
+    assert_eq!(contract.owner_of(&2u32), new_owner);
+
+    contract.burn(&recipient, &1u32);
+    assert!(!contract.is_soulbound(&1u32));
+}
+
+#[test]
+#[should_panic]
+fn test_recover_token_unauthorized() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let new_owner = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.mint(&recipient, &1u32);
+
+    env.mock_auths(&[MockAuth {
+        address: &new_owner,
+        invoke: &MockAuthInvoke {
+            contract: &contract.address,
+            fn_name: "recover_token",
+            args: (&recipient, &new_owner, 1u32).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+
+    contract.recover_token(&recipient, &new_owner, &1u32);
+}
+
+#[test]
+fn test_minter_quota() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let minter = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    assert!(contract.is_minter(&owner));
+    assert!(!contract.is_minter(&minter));
+
+    contract.add_minter(&minter, &Some(3u32), &None);
+    assert!(contract.is_minter(&minter));
+
+    contract.minter_mint(&recipient, &1u32, &minter);
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            minter.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract.address.clone(),
+                    Symbol::new(&env, "minter_mint"),
+                    (recipient.clone(), 1u32, minter.clone()).into_val(&env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+
+    let token_data = TokenData {
+        session_id: String::from_str(&env, "session_1"),
+        resource: String::from_str(&env, "badge"),
+    };
+    contract.minter_bulk_mint_with_data(
+        &vec![
+            &env,
+            (recipient.clone(), 2u32, token_data.clone()),
+            (recipient.clone(), 3u32, token_data.clone()),
+        ],
+        &minter,
+    );
+    assert_eq!(contract.get_minter(&minter).unwrap().quota, Some(0));
+    assert_eq!(
+        contract.try_mint_auto(&recipient, &minter),
+        Err(Ok(NonFungibleTokenContractError::MinterQuotaExceeded.into()))
+    );
+
+    // The owner isn't limited by minter quotas
+    contract.mint_auto(&recipient, &owner);
+    assert_eq!(contract.total_supply(), 4);
+}
+
+#[test]
+fn test_minter_expiry_and_removal() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let minter = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.add_minter(&minter, &None, &Some(INITIAL_SEQUENCE_NUMBER + 10));
+    contract.minter_mint(&recipient, &1u32, &minter);
+
+    env.ledger()
+        .set_sequence_number(INITIAL_SEQUENCE_NUMBER + 11);
+    assert!(!contract.is_minter(&minter));
+    assert_eq!(
+        contract.try_minter_mint(&recipient, &2u32, &minter),
+        Err(Ok(NonFungibleTokenContractError::MinterExpired.into()))
+    );
+
+    contract.add_minter(&minter, &None, &None);
+    contract.minter_mint(&recipient, &2u32, &minter);
+
+    contract.remove_minter(&minter);
+    assert!(!contract.is_minter(&minter));
+    assert_eq!(
+        contract.try_minter_mint(&recipient, &3u32, &minter),
+        Err(Ok(NonFungibleTokenContractError::NotMinter.into()))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_add_minter_unauthorized() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let minter = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    env.mock_auths(&[MockAuth {
+        address: &minter,
+        invoke: &MockAuthInvoke {
+            contract: &contract.address,
+            fn_name: "add_minter",
+            args: (&minter, None::<u32>, None::<u32>).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+
+    contract.add_minter(&minter, &None, &None);
+}
+
+#[test]
+fn test_transfer_ownership() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let new_owner = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    assert_eq!(contract.get_owner(), Some(owner.clone()));
+    assert_eq!(
+        contract.try_accept_ownership(),
+        Err(Ok(NonFungibleTokenContractError::NoPendingOwner.into()))
+    );
+
+    contract.propose_owner(&new_owner);
+    assert_eq!(contract.get_pending_owner(), Some(new_owner.clone()));
+    // Ownership doesn't move until the new owner accepts it
+    assert_eq!(contract.get_owner(), Some(owner.clone()));
+
+    contract.accept_ownership();
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            new_owner.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract.address.clone(),
+                    Symbol::new(&env, "accept_ownership"),
+                    ().into_val(&env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(contract.get_owner(), Some(new_owner.clone()));
+    assert_eq!(contract.get_pending_owner(), None);
+    assert!(contract.is_minter(&new_owner));
+    assert!(!contract.is_minter(&owner));
+}
+
+#[test]
+fn test_renounce_ownership() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let minter = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.add_minter(&minter, &None, &None);
+    contract.renounce_ownership();
+
+    assert_eq!(contract.get_owner(), None);
+    assert_eq!(
+        contract.try_set_metadata_uri(&String::from_str(&env, "https://new.com/")),
+        Err(Ok(NonFungibleTokenContractError::UnsetOwner.into()))
+    );
+    // Registered minters keep minting
+    contract.minter_mint(&recipient, &1u32, &minter);
+    assert_eq!(contract.owner_of(&1u32), recipient);
+}
+
+#[test]
+#[should_panic]
+fn test_propose_owner_unauthorized() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let new_owner = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    env.mock_auths(&[MockAuth {
+        address: &new_owner,
+        invoke: &MockAuthInvoke {
+            contract: &contract.address,
+            fn_name: "propose_owner",
+            args: (&new_owner,).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+
+    contract.propose_owner(&new_owner);
+}
+
+#[test]
+fn test_redeem_voucher() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+    let public_key = BytesN::from_array(&env, &signing_key.verifying_key().to_bytes());
+    contract.set_voucher_signer(&owner, &Some(public_key.clone()));
+    assert_eq!(contract.get_voucher_signer(&owner), Some(public_key));
+
+    let voucher = MintVoucher {
+        minter: owner.clone(),
+        to: recipient.clone(),
+        token_id: None,
+        data: TokenData {
+            session_id: String::from_str(&env, "session_1"),
+            resource: String::from_str(&env, "badge"),
+        },
+        nonce: 1,
+        expires_at_ledger: INITIAL_SEQUENCE_NUMBER + 100,
+    };
+    let signature = sign_voucher(&env, &contract, &signing_key, &voucher);
+
+    let token_id = contract.redeem_voucher(&voucher, &signature);
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            recipient.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract.address.clone(),
+                    Symbol::new(&env, "redeem_voucher"),
+                    (voucher.clone(), signature.clone()).into_val(&env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(token_id, 0);
+    assert_eq!(contract.owner_of(&token_id), recipient);
+    assert_eq!(
+        contract.get_token_data(&token_id).resource,
+        voucher.data.resource
+    );
+    assert!(contract.is_voucher_used(&owner, &1));
+    assert_eq!(
+        contract.try_redeem_voucher(&voucher, &signature),
+        Err(Ok(NonFungibleTokenContractError::VoucherUsed.into()))
+    );
+}
+
+#[test]
+fn test_redeem_voucher_rejected() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let minter = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    let signing_key = SigningKey::from_bytes(&[2u8; 32]);
+    let public_key = BytesN::from_array(&env, &signing_key.verifying_key().to_bytes());
+    contract.add_minter(&minter, &Some(1u32), &None);
+    contract.set_voucher_signer(&minter, &Some(public_key));
+
+    let mut voucher = MintVoucher {
+        minter: minter.clone(),
+        to: recipient.clone(),
+        token_id: Some(7),
+        data: TokenData {
+            session_id: String::from_str(&env, "session_1"),
+            resource: String::from_str(&env, "badge"),
+        },
+        nonce: 1,
+        expires_at_ledger: INITIAL_SEQUENCE_NUMBER,
+    };
+
+    contract.revoke_voucher(&minter, &1);
+    let signature = sign_voucher(&env, &contract, &signing_key, &voucher);
+    assert_eq!(
+        contract.try_redeem_voucher(&voucher, &signature),
+        Err(Ok(NonFungibleTokenContractError::VoucherUsed.into()))
+    );
+
+    voucher.nonce = 2;
+    env.ledger()
+        .set_sequence_number(INITIAL_SEQUENCE_NUMBER + 1);
+    let signature = sign_voucher(&env, &contract, &signing_key, &voucher);
+    assert_eq!(
+        contract.try_redeem_voucher(&voucher, &signature),
+        Err(Ok(NonFungibleTokenContractError::VoucherExpired.into()))
+    );
+
+    voucher.expires_at_ledger = INITIAL_SEQUENCE_NUMBER + 100;
+    let signature = sign_voucher(&env, &contract, &signing_key, &voucher);
+    assert_eq!(contract.redeem_voucher(&voucher, &signature), 7);
+
+    // The voucher mint used the minter's whole quota
+    voucher.nonce = 3;
+    voucher.token_id = Some(8);
+    let signature = sign_voucher(&env, &contract, &signing_key, &voucher);
+    assert_eq!(
+        contract.try_redeem_voucher(&voucher, &signature),
+        Err(Ok(NonFungibleTokenContractError::MinterQuotaExceeded.into()))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_redeem_voucher_invalid_signature() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+    let public_key = BytesN::from_array(&env, &signing_key.verifying_key().to_bytes());
+    contract.set_voucher_signer(&owner, &Some(public_key));
+
+    let mut voucher = MintVoucher {
+        minter: owner.clone(),
+        to: recipient.clone(),
+        token_id: Some(1),
+        data: TokenData {
+            session_id: String::from_str(&env, "session_1"),
+            resource: String::from_str(&env, "badge"),
+        },
+        nonce: 1,
+        expires_at_ledger: INITIAL_SEQUENCE_NUMBER + 100,
+    };
+    let signature = sign_voucher(&env, &contract, &signing_key, &voucher);
+
+    // The recipient can't change what the minter signed
+    voucher.token_id = Some(2);
+    contract.redeem_voucher(&voucher, &signature);
+}
+
+#[test]
+fn test_claim_mint() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    let session_id = String::from_str(&env, "session_1");
+    let data1 = TokenData {
+        session_id: session_id.clone(),
+        resource: String::from_str(&env, "t-shirt"),
+    };
+    let data2 = TokenData {
+        session_id: session_id.clone(),
+        resource: String::from_str(&env, "mug"),
+    };
+    let leaf1 = hash_leaf(
+        &env,
+        &MintLeaf {
+            index: 0,
+            address: user1.clone(),
+            session_id: session_id.clone(),
+            resource: data1.resource.clone(),
+        },
+    );
+    let leaf2 = hash_leaf(
+        &env,
+        &MintLeaf {
+            index: 1,
+            address: user2.clone(),
+            session_id: session_id.clone(),
+            resource: data2.resource.clone(),
+        },
+    );
+    let root = hash_pair(&env, &leaf1, &leaf2);
+
+    assert_eq!(
+        contract.try_claim_mint(&0, &user1, &data1, &vec![&env, leaf2.clone()]),
+        Err(Ok(NonFungibleTokenContractError::UnsetMintRoot.into()))
+    );
+
+    contract.set_mint_root(&session_id, &Some(root.clone()));
+    assert_eq!(contract.get_mint_root(&session_id), Some(root));
+
+    let token_id = contract.claim_mint(&0, &user1, &data1, &vec![&env, leaf2.clone()]);
+    assert_eq!(contract.owner_of(&token_id), user1);
+    assert_eq!(contract.get_token_data(&token_id).resource, data1.resource);
+    assert!(contract.is_mint_claimed(&session_id, &0));
+    assert_eq!(
+        contract.try_claim_mint(&0, &user1, &data1, &vec![&env, leaf2.clone()]),
+        Err(Ok(NonFungibleTokenContractError::MintAlreadyClaimed.into()))
+    );
+
+    // Leaves can't be claimed with someone else's data
+    assert_eq!(
+        contract.try_claim_mint(&1, &user2, &data1, &vec![&env, leaf1.clone()]),
+        Err(Ok(NonFungibleTokenContractError::InvalidProof.into()))
+    );
+
+    let token_id = contract.claim_mint(&1, &user2, &data2, &vec![&env, leaf1]);
+    assert_eq!(contract.owner_of(&token_id), user2);
+    assert_eq!(contract.total_supply(), 2);
+}
+
+#[test]
+#[should_panic]
+fn test_set_mint_root_unauthorized() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let unauthorized = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    let session_id = String::from_str(&env, "session_1");
+    let root = BytesN::from_array(&env, &[0u8; 32]);
+
+    env.mock_auths(&[MockAuth {
+        address: &unauthorized,
+        invoke: &MockAuthInvoke {
+            contract: &contract.address,
+            fn_name: "set_mint_root",
+            args: (&session_id, Some(root.clone())).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+
+    contract.set_mint_root(&session_id, &Some(root));
+}
+
+#[test]
+fn test_set_max_supply() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 2u32);
+
+    contract.mint(&recipient, &1u32);
+    contract.mint(&recipient, &2u32);
+    assert_eq!(
+        contract.try_mint(&recipient, &3u32),
+        Err(Ok(NonFungibleTokenContractError::SupplyExhausted.into()))
+    );
+
+    contract.set_max_supply(&3u32);
+    contract.mint(&recipient, &3u32);
+    assert_eq!(
+        contract.try_set_max_supply(&2u32),
+        Err(Ok(
+            NonFungibleTokenContractError::MaxSupplyBelowMinted.into()
+        ))
+    );
+
+    contract.freeze_supply();
+    assert!(contract.is_supply_frozen());
+    assert_eq!(
+        contract.try_set_max_supply(&10u32),
+        Err(Ok(NonFungibleTokenContractError::SupplyFrozen.into()))
+    );
+    assert_eq!(
+        contract.try_set_session_max_supply(&String::from_str(&env, "session_1"), &Some(10u32)),
+        Err(Ok(NonFungibleTokenContractError::SupplyFrozen.into()))
+    );
+}
+
+#[test]
+fn test_session_max_supply() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 10u32);
+
+    let session_id = String::from_str(&env, "session_1");
+    let token_data = TokenData {
+        session_id: session_id.clone(),
+        resource: String::from_str(&env, "t-shirt"),
+    };
+    let other_data = TokenData {
+        session_id: String::from_str(&env, "session_2"),
+        resource: String::from_str(&env, "mug"),
+    };
+
+    assert_eq!(contract.get_session_remaining(&session_id), 10);
+    contract.set_session_max_supply(&session_id, &Some(2u32));
+    assert_eq!(contract.get_session_max_supply(&session_id), Some(2));
+
+    contract.mint_with_data(&recipient, &1u32, &token_data);
+    contract.mint_with_data_auto(&recipient, &token_data, &owner);
+    assert_eq!(contract.get_session_minted(&session_id), 2);
+    assert_eq!(contract.get_session_remaining(&session_id), 0);
+    assert_eq!(
+        contract.try_mint_with_data(&recipient, &3u32, &token_data),
+        Err(Ok(
+            NonFungibleTokenContractError::SessionSupplyExhausted.into()
+        ))
+    );
+    assert_eq!(
+        contract.try_set_session_max_supply(&session_id, &Some(1u32)),
+        Err(Ok(
+            NonFungibleTokenContractError::MaxSupplyBelowMinted.into()
+        ))
+    );
+
+    // Other sessions are only limited by the collection supply
+    contract.mint_with_data(&recipient, &3u32, &other_data);
+    assert_eq!(contract.get_session_remaining(&other_data.session_id), 7);
+
+    contract.set_session_max_supply(&session_id, &None);
+    contract.mint_with_data(&recipient, &4u32, &token_data);
+    assert_eq!(contract.get_session_minted(&session_id), 3);
+}
+
+#[test]
+fn test_token_data_events() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    let token_data = TokenData {
+        session_id: String::from_str(&env, "session_1"),
+        resource: String::from_str(&env, "t-shirt"),
+    };
+
+    contract.mint_with_data(&recipient, &1u32, &token_data);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("mint_data"), recipient.clone(), 1u32).into_val(&env),
+                token_data.clone().into_val(&env),
+            ),
+            (
+                contract.address.clone(),
+                (symbol_short!("mint"), recipient.clone()).into_val(&env),
+                1u32.into_val(&env),
+            ),
+        ]
+    );
+
+    contract.bulk_mint_with_data(&vec![&env, (recipient.clone(), 2u32, token_data.clone())]);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("mint_data"), recipient.clone(), 2u32).into_val(&env),
+                token_data.clone().into_val(&env),
+            ),
+            (
+                contract.address.clone(),
+                (symbol_short!("mint"), recipient.clone()).into_val(&env),
+                2u32.into_val(&env),
+            ),
+            (
+                contract.address.clone(),
+                (symbol_short!("bulk_mint"), owner.clone()).into_val(&env),
+                1u32.into_val(&env),
+            ),
+        ]
+    );
+
+    contract.set_metadata_uri(&String::from_str(&env, "https://new.com/"));
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("meta_uri"),).into_val(&env),
+                (
+                    String::from_str(&env, "https://nft.com/"),
+                    String::from_str(&env, "https://new.com/"),
+                )
+                    .into_val(&env),
+            ),
+        ]
+    );
+
+    contract.burn(&recipient, &1u32);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("burn"), recipient.clone()).into_val(&env),
+                1u32.into_val(&env),
+            ),
+            (
+                contract.address.clone(),
+                (symbol_short!("burn_data"), recipient.clone(), 1u32).into_val(&env),
+                Some(token_data).into_val(&env),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_batch_transfer() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    for token_id in 1..=4u32 {
+        contract.mint(&sender, &token_id);
+    }
+
+    let token_ids = vec![&env, 1u32, 4u32];
+    contract.batch_transfer(&sender, &recipient, &token_ids);
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            sender.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract.address.clone(),
+                    Symbol::new(&env, "batch_transfer"),
+                    (sender.clone(), recipient.clone(), token_ids).into_val(&env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(contract.balance(&sender), 2);
+    assert_eq!(contract.balance(&recipient), 2);
+    assert_eq!(
+        contract.get_owner_tokens(&sender, &0, &10),
+        vec![&env, 3, 2]
+    );
+    assert_eq!(
+        contract.get_owner_tokens(&recipient, &0, &10),
+        vec![&env, 1, 4]
+    );
+
+    // Nothing moves when one of the tokens isn't owned by the sender
+    assert_eq!(
+        contract.try_batch_transfer(&sender, &recipient, &vec![&env, 2u32, 4u32]),
+        Err(Ok(NonFungibleTokenContractError::NotTokenOwner.into()))
+    );
+    assert_eq!(contract.owner_of(&2u32), sender);
+}
+
+#[test]
+fn test_batch_transfer_to_many() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    for token_id in 1..=4u32 {
+        contract.mint(&sender, &token_id);
+    }
+    let token_data = TokenData {
+        session_id: String::from_str(&env, "session_1"),
+        resource: String::from_str(&env, "badge"),
+    };
+    contract.mint_soulbound(&sender, &5u32, &token_data, &owner);
+
+    assert_eq!(
+        contract.try_batch_transfer_to_many(
+            &sender,
+            &vec![&env, (recipient1.clone(), 1u32), (recipient2.clone(), 5u32)],
+        ),
+        Err(Ok(NonFungibleTokenContractError::Soulbound.into()))
+    );
+
+    contract.batch_transfer_to_many(
+        &sender,
+        &vec![
+            &env,
+            (recipient1.clone(), 1u32),
+            (recipient2.clone(), 2u32),
+            (recipient1.clone(), 3u32),
+        ],
+    );
+    assert_eq!(contract.owner_of(&1u32), recipient1);
+    assert_eq!(contract.owner_of(&2u32), recipient2);
+    assert_eq!(contract.owner_of(&3u32), recipient1);
+    assert_eq!(
+        contract.get_owner_tokens(&sender, &0, &10),
+        vec![&env, 5, 4]
+    );
+    assert_eq!(
+        contract.get_owner_tokens(&recipient1, &0, &10),
+        vec![&env, 1, 3]
+    );
+    assert_eq!(
+        contract.get_owner_tokens(&recipient2, &0, &10),
+        vec![&env, 2]
+    );
+}
+
+#[test]
+fn test_freeze_metadata() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.mint(&recipient, &1u32);
+    contract.mint(&recipient, &2u32);
+
+    assert!(!contract.is_token_data_frozen(&1u32));
+    contract.freeze_token_data(&1u32);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("data_frz"), 1u32).into_val(&env),
+                ().into_val(&env),
+            ),
+        ]
+    );
+    assert!(contract.is_token_data_frozen(&1u32));
+    assert!(!contract.is_token_data_frozen(&2u32));
+
+    assert!(!contract.is_metadata_frozen());
+    contract.freeze_metadata();
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("meta_frz"),).into_val(&env),
+                ().into_val(&env),
+            ),
+        ]
+    );
+    assert!(contract.is_metadata_frozen());
+    assert!(contract.is_token_data_frozen(&2u32));
+    assert_eq!(
+        contract.try_set_metadata_uri(&String::from_str(&env, "https://new.com/")),
+        Err(Ok(NonFungibleTokenContractError::MetadataFrozen.into()))
+    );
+    assert_eq!(
+        contract.try_set_session_base_uri(
+            &String::from_str(&env, "session_1"),
+            &String::from_str(&env, "ipfs://QmSession1/"),
+        ),
+        Err(Ok(NonFungibleTokenContractError::MetadataFrozen.into()))
+    );
+    assert_eq!(
+        contract.get_token_metadata().base_uri,
+        String::from_str(&env, "https://nft.com/")
+    );
+}
+
+#[test]
+fn test_burn_from_approved_operator() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let operator = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    let token_data = TokenData {
+        session_id: String::from_str(&env, "session_1"),
+        resource: String::from_str(&env, "badge"),
+    };
+    contract.mint_with_data(&recipient, &0u32, &token_data);
+    contract.mint_with_data(&recipient, &1u32, &token_data);
+
+    contract.approve(&recipient, &operator, &0u32, &1000u32);
+    contract.burn_from(&operator, &recipient, &0u32);
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            operator.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract.address.clone(),
+                    Symbol::new(&env, "burn_from"),
+                    (operator.clone(), recipient.clone(), 0u32).into_val(&env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+
+    assert!(contract.is_burned(&0u32));
+    assert_eq!(contract.balance(&recipient), 1);
+    assert_eq!(contract.total_minted(), 2);
+    assert_eq!(contract.circulating_supply(), 1);
+    assert_eq!(contract.total_supply(), 1);
+    assert_eq!(
+        contract.try_get_token_data(&0u32),
+        Err(Ok(NonFungibleTokenContractError::UnsetTokenData.into()))
+    );
+
+    // Burned IDs are never minted again
+    assert_eq!(
+        contract.try_mint(&recipient, &0u32),
+        Err(Ok(NonFungibleTokenContractError::TokenBurned.into()))
+    );
+    assert_eq!(contract.mint_auto(&recipient, &owner), 2);
+
+    // The approval doesn't extend to the holder's other tokens
+    assert!(contract
+        .try_burn_from(&operator, &recipient, &1u32)
+        .is_err());
+}
+
+#[test]
+fn test_update_token_data() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let minter = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    let token_data = TokenData {
+        session_id: String::from_str(&env, "sesion_1"),
+        resource: String::from_str(&env, "badge"),
+    };
+    let fixed_data = TokenData {
+        session_id: String::from_str(&env, "session_1"),
+        resource: String::from_str(&env, "badge"),
+    };
+    contract.mint_with_data(&recipient, &1u32, &token_data);
+    assert_eq!(contract.get_token_data_revision(&1u32), 0);
+
+    contract.add_minter(&minter, &Some(0u32), &None);
+    contract.update_token_data(&1u32, &fixed_data, &minter);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("data_upd"), 1u32).into_val(&env),
+                (Some(token_data.clone()), fixed_data.clone(), 1u32).into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(contract.get_token_data(&1u32), fixed_data);
+    assert_eq!(contract.get_token_data_revision(&1u32), 1);
+    assert_eq!(contract.get_session_minted(&token_data.session_id), 0);
+    assert_eq!(contract.get_session_minted(&fixed_data.session_id), 1);
+
+    contract.freeze_token_data(&1u32);
+    assert_eq!(
+        contract.try_update_token_data(&1u32, &token_data, &owner),
+        Err(Ok(NonFungibleTokenContractError::MetadataFrozen.into()))
+    );
+
+    let stranger = Address::generate(&env);
+    assert_eq!(
+        contract.try_update_token_data(&1u32, &token_data, &stranger),
+        Err(Ok(NonFungibleTokenContractError::NotMinter.into()))
+    );
+}
+
+#[test]
+fn test_redeem() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let holder = Address::generate(&env);
+    let vendor = Address::generate(&env);
+    let receiver = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.mint(&holder, &1u32);
+    contract.mint(&holder, &2u32);
+    contract.add_vendor(&vendor);
+    assert!(contract.is_vendor(&vendor));
+    assert!(!contract.is_redeemed(&1u32));
+    assert_eq!(contract.redemption_info(&1u32), None);
+
+    env.ledger().set_timestamp(1_700_000_000);
+    contract.redeem(&1u32, &vendor);
+    assert_eq!(
+        env.auths(),
+        std::vec![
+            (
+                holder.clone(),
+                AuthorizedInvocation {
+                    function: AuthorizedFunction::Contract((
+                        contract.address.clone(),
+                        symbol_short!("redeem"),
+                        (1u32, vendor.clone()).into_val(&env),
+                    )),
+                    sub_invocations: std::vec![]
+                }
+            ),
+            (
+                vendor.clone(),
+                AuthorizedInvocation {
+                    function: AuthorizedFunction::Contract((
+                        contract.address.clone(),
+                        symbol_short!("redeem"),
+                        (1u32, vendor.clone()).into_val(&env),
+                    )),
+                    sub_invocations: std::vec![]
+                }
+            )
+        ]
+    );
+    let info = RedemptionInfo {
+        vendor: vendor.clone(),
+        timestamp: 1_700_000_000,
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("redeem"), holder.clone(), 1u32).into_val(&env),
+                info.clone().into_val(&env),
+            )
+        ]
+    );
+    assert!(contract.is_redeemed(&1u32));
+    assert_eq!(contract.redemption_info(&1u32), Some(info));
+
+    // Redeemed tokens stay transferable unless the owner locks them
+    contract.transfer(&holder, &receiver, &1u32);
+    assert!(contract.is_redeemed(&1u32));
+
+    contract.set_redeemed_soulbound(&true);
+    assert!(contract.is_soulbound(&1u32));
+    assert!(!contract.is_soulbound(&2u32));
+    assert_eq!(
+        contract.try_transfer(&receiver, &holder, &1u32),
+        Err(Ok(NonFungibleTokenContractError::Soulbound.into()))
+    );
+    contract.transfer(&holder, &receiver, &2u32);
+
+    contract.burn(&receiver, &1u32);
+    assert!(!contract.is_redeemed(&1u32));
+}
+
+#[test]
+fn test_redeem_rejected() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let holder = Address::generate(&env);
+    let vendor = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.mint(&holder, &1u32);
+    assert_eq!(
+        contract.try_redeem(&1u32, &vendor),
+        Err(Ok(NonFungibleTokenContractError::NotVendor.into()))
+    );
+
+    contract.add_vendor(&vendor);
+    contract.redeem(&1u32, &vendor);
+    assert_eq!(
+        contract.try_redeem(&1u32, &vendor),
+        Err(Ok(NonFungibleTokenContractError::AlreadyRedeemed.into()))
+    );
+
+    contract.remove_vendor(&vendor);
+    assert!(!contract.is_vendor(&vendor));
+    contract.mint(&holder, &2u32);
+    assert_eq!(
+        contract.try_redeem(&2u32, &vendor),
+        Err(Ok(NonFungibleTokenContractError::NotVendor.into()))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_add_vendor_unauthorized() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let vendor = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    env.mock_auths(&[MockAuth {
+        address: &vendor,
+        invoke: &MockAuthInvoke {
+            contract: &contract.address,
+            fn_name: "add_vendor",
+            args: (&vendor,).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+
+    contract.add_vendor(&vendor);
+}
+
+#[test]
+fn test_set_user() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let holder = Address::generate(&env);
+    let operator = Address::generate(&env);
+    let user = Address::generate(&env);
+    let receiver = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.mint(&holder, &1u32);
+    assert_eq!(contract.user_of(&1u32), None);
+    assert_eq!(contract.user_expires(&1u32), 0);
+
+    env.ledger().set_timestamp(1_000);
+    contract.set_user(&holder, &1u32, &Some(user.clone()), &2_000u64);
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            holder.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract.address.clone(),
+                    Symbol::new(&env, "set_user"),
+                    (holder.clone(), 1u32, Some(user.clone()), 2_000u64).into_val(&env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("user_upd"), 1u32).into_val(&env),
+                (Some(user.clone()), 2_000u64).into_val(&env),
+            )
+        ]
+    );
+    assert_eq!(contract.user_of(&1u32), Some(user.clone()));
+    assert_eq!(contract.user_expires(&1u32), 2_000);
+    // Using the token doesn't move ownership
+    assert_eq!(contract.owner_of(&1u32), holder);
+
+    env.ledger().set_timestamp(2_001);
+    assert_eq!(contract.user_of(&1u32), None);
+    assert_eq!(contract.user_expires(&1u32), 2_000);
+
+    // Approved operators can set the user too, and the user goes away with the token
+    contract.approve_for_all(&holder, &operator, &(INITIAL_SEQUENCE_NUMBER + 3));
+    contract.set_user(&operator, &1u32, &Some(user.clone()), &3_000u64);
+    assert_eq!(contract.user_of(&1u32), Some(user.clone()));
+
+    contract.transfer(&holder, &receiver, &1u32);
+    assert_eq!(contract.user_of(&1u32), None);
+    assert_eq!(contract.user_expires(&1u32), 0);
+
+    contract.set_user(&receiver, &1u32, &Some(user.clone()), &3_000u64);
+    contract.set_user(&receiver, &1u32, &None, &0u64);
+    assert_eq!(contract.user_of(&1u32), None);
+
+    assert_eq!(
+        contract.try_set_user(&user, &1u32, &Some(user.clone()), &3_000u64),
+        Err(Ok(NonFungibleTokenError::InsufficientApproval.into()))
+    );
+}
+
+#[test]
+fn test_pause() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let holder = Address::generate(&env);
+    let receiver = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    let vendor = Address::generate(&env);
+    contract.mint(&holder, &1u32);
+    contract.add_vendor(&vendor);
+    assert!(!contract.paused());
+
+    contract.pause();
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("paused"),).into_val(&env),
+                ().into_val(&env),
+            )
+        ]
+    );
+    assert!(contract.paused());
+
+    let live_until_ledger = INITIAL_SEQUENCE_NUMBER + 3;
+    let paused = Err(Ok(NonFungibleTokenContractError::Paused.into()));
+    assert_eq!(contract.try_transfer(&holder, &receiver, &1u32), paused);
+    assert_eq!(
+        contract.try_transfer_from(&owner, &holder, &receiver, &1u32),
+        paused
+    );
+    assert_eq!(
+        contract.try_batch_transfer(&holder, &receiver, &vec![&env, 1]),
+        paused
+    );
+    assert_eq!(
+        contract.try_approve(&holder, &receiver, &1u32, &live_until_ledger),
+        paused
+    );
+    assert_eq!(
+        contract.try_approve_for_all(&holder, &receiver, &live_until_ledger),
+        paused
+    );
+    assert_eq!(contract.try_burn(&holder, &1u32), paused);
+    assert_eq!(contract.try_burn_from(&owner, &holder, &1u32), paused);
+    assert_eq!(contract.try_redeem(&1u32, &vendor), paused);
+    assert_eq!(
+        contract.try_set_user(&holder, &1u32, &Some(receiver.clone()), &3_000u64),
+        paused
+    );
+
+    // Minting and recovery keep working
+    contract.mint(&holder, &2u32);
+    contract.recover_token(&holder, &receiver, &2u32);
+    assert_eq!(contract.owner_of(&2u32), receiver);
+
+    contract.unpause();
+    assert!(!contract.paused());
+    contract.transfer(&holder, &receiver, &1u32);
+    assert_eq!(contract.owner_of(&1u32), receiver);
+}
+
+#[test]
+fn test_freeze_token() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let holder = Address::generate(&env);
+    let thief = Address::generate(&env);
+    let vendor = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    contract.mint(&thief, &1u32);
+    contract.mint(&thief, &2u32);
+    contract.add_vendor(&vendor);
+    assert!(!contract.is_token_frozen(&1u32));
+
+    contract.freeze_token(&1u32);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("frozen"), 1u32).into_val(&env),
+                ().into_val(&env),
+            )
+        ]
+    );
+    assert!(contract.is_token_frozen(&1u32));
+
+    let frozen = Err(Ok(NonFungibleTokenContractError::TokenFrozen.into()));
+    assert_eq!(contract.try_transfer(&thief, &holder, &1u32), frozen);
+    assert_eq!(
+        contract.try_approve(&thief, &holder, &1u32, &(INITIAL_SEQUENCE_NUMBER + 3)),
+        frozen
+    );
+    assert_eq!(contract.try_burn(&thief, &1u32), frozen);
+    assert_eq!(contract.try_redeem(&1u32, &vendor), frozen);
+    assert_eq!(
+        contract.try_set_user(&thief, &1u32, &Some(holder.clone()), &3_000u64),
+        frozen
+    );
+    assert_eq!(
+        contract.try_batch_transfer(&thief, &holder, &vec![&env, 2, 1]),
+        frozen
+    );
+    assert_eq!(contract.owner_of(&2u32), thief);
+
+    // Other tokens aren't affected, and the owner can still give the token back
+    contract.transfer(&thief, &holder, &2u32);
+    contract.recover_token(&thief, &holder, &1u32);
+    assert_eq!(contract.owner_of(&1u32), holder);
+
+    contract.unfreeze_token(&1u32);
+    assert!(!contract.is_token_frozen(&1u32));
+    contract.burn(&holder, &1u32);
+
+    assert_eq!(
+        contract.try_freeze_token(&1u32),
+        Err(Ok(NonFungibleTokenError::NonExistentToken.into()))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_pause_unauthorized() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let holder = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+
+    env.mock_auths(&[MockAuth {
+        address: &holder,
+        invoke: &MockAuthInvoke {
+            contract: &contract.address,
+            fn_name: "pause",
+            args: ().into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+
+    contract.pause();
+}
+
+#[test]
+fn test_mint_consecutive() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 2000u32);
+    let data = TokenData {
+        session_id: String::from_str(&env, "session_1"),
+        resource: String::from_str(&env, "t-shirt"),
+    };
+
+    assert_eq!(contract.mint_auto(&user1, &owner), 0);
+    assert_eq!(
+        contract.mint_consecutive(&user1, &1000u32, &Some(data.clone()), &owner),
+        1
+    );
+
+    assert_eq!(contract.balance(&user1), 1001);
+    assert_eq!(contract.owner_of(&1u32), user1);
+    assert_eq!(contract.owner_of(&1000u32), user1);
+    assert!(contract.try_owner_of(&1001u32).is_err());
+    assert_eq!(contract.total_minted(), 1001);
+    assert_eq!(contract.circulating_supply(), 1001);
+    assert_eq!(
+        contract.get_session_minted(&String::from_str(&env, "session_1")),
+        1000
+    );
+    assert_eq!(
+        contract.get_owner_tokens(&user1, &0, &3),
+        vec![&env, 0, 1, 2]
+    );
+    assert_eq!(contract.token_of_owner_by_index(&user1, &1000), 1000);
+    assert_eq!(contract.tokens(&998, &10), vec![&env, 998, 999, 1000]);
+    assert_eq!(contract.get_token_data(&500u32), data);
+    assert_eq!(
+        contract.token_uri(&500u32),
+        String::from_str(&env, "https://nft.com/500")
+    );
+
+    // Tokens leaving the range take the last token of the owner's list into their slot
+    contract.transfer(&user1, &user2, &500u32);
+    assert_eq!(contract.owner_of(&500u32), user2);
+    assert_eq!(contract.owner_of(&499u32), user1);
+    assert_eq!(contract.token_of_owner_by_index(&user1, &500), 1000);
+    assert!(contract.try_token_of_owner_by_index(&user1, &1000).is_err());
+
+    contract.batch_transfer(&user1, &user2, &vec![&env, 1, 2]);
+    assert_eq!(
+        contract.get_owner_tokens(&user1, &0, &3),
+        vec![&env, 0, 999, 998]
+    );
+
+    let live_until_ledger = INITIAL_SEQUENCE_NUMBER + 3;
+    contract.approve(&user1, &owner, &4u32, &live_until_ledger);
+    contract.transfer_from(&owner, &user1, &user2, &4u32);
+    assert_eq!(contract.owner_of(&4u32), user2);
+    assert_eq!(contract.get_token_data(&4u32), data);
+
+    contract.burn(&user1, &3u32);
+    assert!(contract.is_burned(&3u32));
+    assert!(contract.try_owner_of(&3u32).is_err());
+    assert!(contract.try_get_token_data(&3u32).is_err());
+    assert_eq!(contract.tokens(&0, &5), vec![&env, 0, 1, 2, 1000, 4]);
+
+    assert_eq!(contract.balance(&user1), 996);
+    assert_eq!(contract.balance(&user2), 4);
+    assert_eq!(
+        contract.get_owner_tokens(&user2, &0, &10),
+        vec![&env, 500, 1, 2, 4]
+    );
+    assert_eq!(contract.circulating_supply(), 1000);
+
+    // Minted ranges are skipped by `mint_auto` and can't be minted explicitly
+    assert_eq!(contract.mint_auto(&user2, &owner), 1001);
+    assert_eq!(
+        contract.try_mint(&user2, &700u32),
+        Err(Ok(NonFungibleTokenContractError::AlreadyMinted.into()))
+    );
+    assert_eq!(
+        contract.try_mint(&user2, &3u32),
+        Err(Ok(NonFungibleTokenContractError::TokenBurned.into()))
+    );
+
+    assert_eq!(
+        contract.mint_consecutive(&user2, &3u32, &None, &owner),
+        1002
+    );
+    assert_eq!(
+        contract.get_owner_tokens(&user2, &0, &10),
+        vec![&env, 500, 1, 2, 4, 1001, 1002, 1003, 1004]
+    );
+    assert!(contract.try_get_token_data(&1003u32).is_err());
+
+    assert_eq!(
+        contract.try_mint_consecutive(&user1, &997u32, &None, &owner),
+        Err(Ok(NonFungibleTokenContractError::SupplyExhausted.into()))
+    );
+    assert_eq!(
+        contract.try_mint_consecutive(&user1, &0u32, &None, &owner),
+        Err(Ok(NonFungibleTokenContractError::InvalidAmount.into()))
+    );
+}
+
+#[test]
+fn test_mint_consecutive_after_other_mints() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let user = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+    let data = TokenData {
+        session_id: String::from_str(&env, "session_1"),
+        resource: String::from_str(&env, "t-shirt"),
+    };
+
+    // Ranges start after the highest ID of any mint path, even once that token is burned
+    contract.bulk_mint_with_data(&vec![&env, (user.clone(), 40u32, data.clone())]);
+    contract.mint_with_data(&user, &50u32, &data);
+    contract.mint(&user, &7u32);
+    contract.burn(&user, &50u32);
+    assert_eq!(contract.mint_consecutive(&user, &3u32, &None, &owner), 51);
+    assert_eq!(contract.owner_of(&53u32), user);
+    assert!(contract.is_burned(&50u32));
+
+    assert_eq!(contract.mint_consecutive(&user, &2u32, &None, &owner), 54);
+    assert_eq!(contract.balance(&user), 7);
+}
+
+#[test]
+fn test_mint_consecutive_random_ids() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let user = Address::generate(&env);
+    let contract = get_contract_with_config(&env, &owner, 100u32, TokenIdStrategy::Random, false);
+
+    assert_eq!(
+        contract.try_mint_consecutive(&user, &3u32, &None, &owner),
+        Err(Ok(
+            NonFungibleTokenContractError::ConsecutiveRandomIds.into()
+        ))
+    );
+
+    // Also after a random mint, which would leave no room for the range
+    contract.mint_auto(&user, &owner);
+    assert_eq!(
+        contract.try_mint_consecutive(&user, &3u32, &None, &owner),
+        Err(Ok(
+            NonFungibleTokenContractError::ConsecutiveRandomIds.into()
+        ))
+    );
+    assert_eq!(contract.balance(&user), 1);
+    assert_eq!(contract.total_minted(), 1);
+}
+
+#[test]
+fn test_mint_consecutive_cost_is_flat() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let holder = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100_000u32);
+    contract.mint_consecutive(&holder, &1u32, &None, &owner);
+
+    env.cost_estimate().budget().reset_default();
+    contract.mint_consecutive(&holder, &10u32, &None, &owner);
+    let small_batch_cost = env.cost_estimate().budget().cpu_instruction_cost();
+
+    env.cost_estimate().budget().reset_default();
+    contract.mint_consecutive(&holder, &50_000u32, &None, &owner);
+    let large_batch_cost = env.cost_estimate().budget().cpu_instruction_cost();
+
+    assert!(large_batch_cost <= small_batch_cost + small_batch_cost / 10);
+    assert_eq!(contract.owner_of(&50_010u32), holder);
+    assert_eq!(contract.balance(&holder), 50_011);
+}
+
+#[test]
+fn test_upgrade() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+    assert_eq!(contract.get_schema_version(), SCHEMA_VERSION);
+
+    // The shipped wasm is the only other build the tests can upload, so this swaps the code
+    // back to it: it checks the code swap and its event, not a migration.
+    let wasm_hash = env.deployer().upload_contract_wasm(legacy_nft::WASM);
+    contract.upgrade(&wasm_hash);
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            owner.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract.address.clone(),
+                    symbol_short!("upgrade"),
+                    (wasm_hash.clone(),).into_val(&env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract.address.clone(),
+                (symbol_short!("upgrade"),).into_val(&env),
+                wasm_hash.into_val(&env),
+            )
+        ]
+    );
+
+    // The contract now runs the uploaded code on the same storage
+    let upgraded = legacy_nft::Client::new(&env, &contract.address);
+    assert_eq!(upgraded.get_max_supply(), 100);
+}
+
+#[test]
+#[should_panic]
+fn test_upgrade_unauthorized() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+    let wasm_hash = env.deployer().upload_contract_wasm(legacy_nft::WASM);
+
+    env.mock_auths(&[MockAuth {
+        address: &attacker,
+        invoke: &MockAuthInvoke {
+            contract: &contract.address,
+            fn_name: "upgrade",
+            args: (wasm_hash.clone(),).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+
+    contract.upgrade(&wasm_hash);
+}
+
+#[test]
+fn test_migrate() {
+    let env = setup_test_env();
+    let owner = Address::generate(&env);
+    let user = Address::generate(&env);
+    let contract = get_contract(&env, &owner, 100u32);
+    contract.mint(&user, &1u32);
+
+    // Collections deployed by this code are already at the current version
+    contract.migrate();
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            owner.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract.address.clone(),
+                    Symbol::new(&env, "migrate"),
+                    ().into_val(&env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(contract.get_schema_version(), SCHEMA_VERSION);
+    assert_eq!(contract.owner_of(&1u32), user);
+    assert_eq!(contract.tokens(&0, &10), vec![&env, 1]);
+}
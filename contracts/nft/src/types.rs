@@ -3,14 +3,124 @@ use soroban_sdk::{contracttype, Address, String};
 #[contracttype]
 pub enum DataKey {
     Owner,
+    SchemaVersion,
+    PendingOwner,
     TotalMinted,
     MaxSupply,
+    SupplyFrozen,
+    MetadataFrozen,
     TokenData(u32),
-    OwnerTokens(Address),
+    OwnerTokenCount(Address),
+    OwnerToken(Address, u32),
+    OwnerTokenIndex(u32),
+    TokenCount,
+    GlobalToken(u32),
+    GlobalTokenIndex(u32),
+    Soulbound,
+    SoulboundToken(u32),
+    SessionBaseUri(String),
+    TokenIdStrategy,
+    NextTokenId,
+    Minter(Address),
+    VoucherSigner(Address),
+    VoucherNonce(Address, u64),
+    MintRoot(String),
+    MintClaimed(String, u32),
+    SessionMaxSupply(String),
+    SessionMinted(String),
+    TokenDataFrozen(u32),
+    BurnedToken(u32),
+    TokenDataRevision(u32),
+    HighestTokenId,
+    ConsecutiveBatchCount,
+    ConsecutiveBatch(u32),
+    BatchTokenData(u32),
+    OwnerBatchCount(Address),
+    OwnerBatch(Address, u32),
+    GlobalBatchCount,
+    GlobalBatch(u32),
+    Vendor(Address),
+    Redemption(u32),
+    RedeemedSoulbound,
+    TokenUser(u32),
+    Paused,
+    TokenFrozen(u32),
 }
 
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TokenIdStrategy {
+    Sequential,
+    Random,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MinterInfo {
+    /// Tokens the minter can still mint, unlimited when `None`
+    pub quota: Option<u32>,
+    /// Last ledger at which the minter can mint, no expiry when `None`
+    pub expires_at_ledger: Option<u32>,
+}
+
+/// Mint right signed off-chain by `minter` with the ed25519 key it registered with
+/// `set_voucher_signer`, and redeemed by `to`.
 #[contracttype]
 #[derive(Clone)]
+pub struct MintVoucher {
+    pub minter: Address,
+    pub to: Address,
+    /// Allocated by the contract when `None`
+    pub token_id: Option<u32>,
+    pub data: TokenData,
+    pub nonce: u64,
+    pub expires_at_ledger: u32,
+}
+
+/// Token IDs minted at once by `mint_consecutive`, whose owner and list positions are stored
+/// once for the whole range. Their data is stored once too, under `BatchTokenData`.
+#[contracttype]
+#[derive(Clone)]
+pub struct ConsecutiveBatch {
+    pub owner: Address,
+    pub first_token_id: u32,
+    pub amount: u32,
+    /// Index of the first token in the owner's token list
+    pub owner_index: u32,
+    /// Index of the first token in the list of existing tokens
+    pub global_index: u32,
+}
+
+/// Record of a token redeemed at a vendor's booth.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RedemptionInfo {
+    pub vendor: Address,
+    /// Ledger timestamp of the redemption, in seconds
+    pub timestamp: u64,
+}
+
+/// Address allowed to use a token without owning it, e.g. a lent event pass.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UserInfo {
+    pub user: Address,
+    /// Last ledger timestamp at which `user` is the token's user, in seconds
+    pub expires: u64,
+}
+
+/// Leaf of a session's allowlist Merkle tree, hashed like `stellar-merkle-distributor` leaves.
+#[contracttype]
+#[derive(Clone)]
+pub struct MintLeaf {
+    pub index: u32,
+    pub address: Address,
+    pub session_id: String,
+    pub resource: String,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TokenData {
     pub session_id: String,
     pub resource: String,